@@ -1,4 +1,5 @@
-use clap::builder::styling;
+use crate::scanner::backend::BACKENDS;
+use clap::builder::{styling, PossibleValuesParser};
 use clap::{Arg, ArgAction, Command};
 use colored::Colorize;
 
@@ -29,6 +30,14 @@ pub fn cli(version: &'static str) -> Command {
                 .conflicts_with("exe")
                 .help("Profile, describing whole setup")
         )
+        .arg(
+            Arg::new("set")
+                .long("set")
+                .value_name("KEY=VALUE")
+                .action(ArgAction::Append)
+                .global(true)
+                .help("Set a variable for the profile, overriding the environment and the built-ins")
+        )
         .arg(
             Arg::new("packages")
                 .short('k')
@@ -36,6 +45,13 @@ pub fn cli(version: &'static str) -> Command {
                 .aliases(["packages", "packags", "packs"])
                 .help("Comma-separated list of packages to account")
         )
+        .arg(
+            Arg::new("scanners")
+                .short('s')
+                .long("scanners")
+                .value_name("list")
+                .help("Comma-separated list of language scanners for script targets: python, perl, shell")
+        )
         .arg(
             Arg::new("invert-filters")
                 .short('i')
@@ -59,6 +75,15 @@ pub fn cli(version: &'static str) -> Command {
                 .value_parser(["free", "clean", "tight", "none"])
                 .help(format!("Auto-add package dependencies.\n{}", " NOTE: This can increase the size, but might not always be useful\n".yellow()))
         )
+        .arg(
+            Arg::new("backend")
+                .short('b')
+                .long("backend")
+                .default_value("auto")
+                .value_name("name")
+                .value_parser(PossibleValuesParser::new(BACKENDS))
+                .help("Package manager of the image. By default it is detected from the image itself")
+        )
         /*
         .arg(
             Arg::new("delta-only")
@@ -116,6 +141,7 @@ pub fn cli(version: &'static str) -> Command {
             Arg::new("debug")
                 .short('d')
                 .long("debug")
+                .global(true)
                 .action(ArgAction::SetTrue)
                 .help("Set debug mode for more verbose output."),
         )
@@ -133,6 +159,29 @@ pub fn cli(version: &'static str) -> Command {
                 .action(ArgAction::SetTrue)
                 .help("Get current version."),
         )
+        // Profile operations
+        .subcommand(
+            Command::new("profile")
+                .about("Profile operations")
+                .subcommand_required(true)
+                .subcommand(
+                    Command::new("check")
+                        .about("Validate the profile against the root filesystem and exit non-zero on problems")
+                        .arg(Arg::new("profile").short('p').long("profile").required(true).help("Profile to validate"))
+                        .arg(Arg::new("root").short('r').long("root").required(true).help("Root filesystem, e.g. mountpoint of an image"))
+                        .arg(
+                            Arg::new("backend")
+                                .short('b')
+                                .long("backend")
+                                .default_value("auto")
+                                .value_name("name")
+                                .value_parser(PossibleValuesParser::new(BACKENDS))
+                                .help("Package manager of the image. By default it is detected from the image itself")
+                        )
+                        .arg(Arg::new("help").short('h').long("help").action(ArgAction::Help).help("Display help"))
+                )
+        )
+        .subcommand_negates_reqs(true)
         .disable_help_flag(true) // Otherwise it is displayed in a wrong position
         .disable_version_flag(true)
         .disable_colored_help(false)
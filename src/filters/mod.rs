@@ -2,4 +2,5 @@ pub mod defs;
 pub mod dirs;
 pub mod intf;
 pub mod resources;
+pub mod rules;
 pub mod texts;
@@ -0,0 +1,88 @@
+/*
+Explicit keep/prune rules of the profile.
+
+A rule is either a literal path, a Unix glob ("*", "**", "?", "[a-z]")
+or a regular expression with the "re:" prefix. Patterns are expanded
+against the files of the root filesystem, so they match real files only.
+A rule, matching a directory, matches everything inside it.
+ */
+
+use glob::{MatchOptions, Pattern};
+use regex::Regex;
+use std::{
+    collections::HashSet,
+    io::{Error, ErrorKind},
+    path::{Path, PathBuf},
+};
+
+#[derive(Clone, Debug)]
+enum Matcher {
+    Path(PathBuf),
+    Glob(Pattern),
+    Regex(Regex),
+}
+
+#[derive(Clone, Debug)]
+pub struct PathRule {
+    rule: String,
+    matcher: Matcher,
+}
+
+impl PathRule {
+    /// Parse a rule. Paths without any glob characters are taken literally.
+    pub fn new(rule: &str) -> Result<Self, Error> {
+        let matcher = if let Some(re) = rule.strip_prefix("re:") {
+            // Regex is always matched against the whole path
+            Matcher::Regex(
+                Regex::new(&format!("^(?:{})$", re))
+                    .map_err(|err| Error::new(ErrorKind::InvalidData, format!("Invalid regex \"{}\": {}", rule, err)))?,
+            )
+        } else if rule.contains(['*', '?', '[']) {
+            Matcher::Glob(
+                Pattern::new(rule)
+                    .map_err(|err| Error::new(ErrorKind::InvalidData, format!("Invalid glob \"{}\": {}", rule, err)))?,
+            )
+        } else {
+            Matcher::Path(PathBuf::from(rule))
+        };
+
+        Ok(PathRule { rule: rule.to_string(), matcher })
+    }
+
+    /// Get the rule, as it is written in the profile
+    pub fn get_rule(&self) -> &str {
+        &self.rule
+    }
+
+    /// Returns true if the path or any of its parent directories matches the rule
+    pub fn matches(&self, p: &Path) -> bool {
+        p.ancestors().any(|p| self.matches_exact(p))
+    }
+
+    fn matches_exact(&self, p: &Path) -> bool {
+        match &self.matcher {
+            Matcher::Path(rp) => rp == p,
+            // "*" does not cross directories, "**" does
+            Matcher::Glob(g) => g.matches_path_with(
+                p,
+                MatchOptions { case_sensitive: true, require_literal_separator: true, require_literal_leading_dot: false },
+            ),
+            Matcher::Regex(re) => re.is_match(p.to_str().unwrap_or_default()),
+        }
+    }
+
+    /// Expand the rule to the paths. Literal path of a file is taken as is, if it exists,
+    /// directories and patterns are matched against the files of the root filesystem.
+    pub fn expand(&self, files: &HashSet<PathBuf>) -> Vec<PathBuf> {
+        let mut out = match &self.matcher {
+            Matcher::Path(p) if p.is_dir() && !p.is_symlink() => {
+                files.iter().filter(|p| self.matches(p)).cloned().collect::<Vec<PathBuf>>()
+            }
+            Matcher::Path(p) if p.exists() => vec![p.to_owned()],
+            Matcher::Path(_) => vec![],
+            _ => files.iter().filter(|p| self.matches(p)).cloned().collect::<Vec<PathBuf>>(),
+        };
+        out.sort();
+        out
+    }
+}
@@ -6,7 +6,8 @@ mod profile;
 mod rootfs;
 mod scanner;
 mod shcall;
-use crate::profile::Profile;
+mod variables;
+use crate::{profile::Profile, variables::Variables};
 use clap::{ArgMatches, Command};
 use colored::Colorize;
 use std::{
@@ -32,9 +33,24 @@ fn is_f(params: &ArgMatches, name: &str) -> bool {
     *params.get_one::<bool>(name).unwrap()
 }
 
+/// Get variables for the profile: environment, built-ins of the image and --set definitions
+fn get_variables(params: &ArgMatches, root: &Path) -> Variables {
+    let mut vars = Variables::new();
+    vars.detect(root);
+
+    for def in params.get_many::<String>("set").unwrap_or_default() {
+        if let Err(err) = vars.define(def) {
+            log::error!("{}", err);
+            process::exit(exitcode::USAGE);
+        }
+    }
+
+    vars
+}
+
 /// Construct profile either from the CLI
 /// or get it from the YAML and configure it.
-fn get_profile(mut cli: Command, params: &ArgMatches) -> Profile {
+fn get_profile(mut cli: Command, params: &ArgMatches, root: &Path) -> Profile {
     let exe = params.get_one::<String>("exe");
     let profile_path = params.get_one::<String>("profile");
 
@@ -58,7 +74,7 @@ fn get_profile(mut cli: Command, params: &ArgMatches) -> Profile {
             .set_img(f(params, "f_pic"));
     } else if let Some(profile_path) = profile_path {
         log::info!("Getting profile at {profile_path}");
-        match Profile::new(Path::new(profile_path)) {
+        match Profile::new(Path::new(profile_path), &get_variables(params, root)) {
             Ok(p) => {
                 profile = p;
 
@@ -95,9 +111,45 @@ fn get_profile(mut cli: Command, params: &ArgMatches) -> Profile {
         }
     }
 
+    // Language scanners apply to the target from CLI as well as to the profile targets
+    if let Some(scanners) = params.get_one::<String>("scanners") {
+        for s in scanners.split(',').map(|s| s.trim()).filter(|s| !s.is_empty()) {
+            profile.add_scanner(s.to_string());
+        }
+    }
+
     profile
 }
 
+/// Validate the profile against the root filesystem.
+/// Exits non-zero if the profile is invalid or does not match the image.
+fn check_profile(params: &ArgMatches) {
+    let profile_path = params.get_one::<String>("profile").unwrap();
+    let rpth = PathBuf::from(params.get_one::<String>("root").unwrap());
+    if !rpth.exists() {
+        log::error!("Mountpoint \"{}\" does not exist or is not accessible", rpth.to_str().unwrap().bright_yellow());
+        process::exit(exitcode::IOERR);
+    }
+
+    log::info!("Checking profile at {profile_path}");
+    let profile = match Profile::new(Path::new(profile_path), &get_variables(params, &rpth)) {
+        Ok(p) => p,
+        Err(err) => {
+            log::error!("{}", err);
+            process::exit(exitcode::DATAERR);
+        }
+    };
+
+    if let Err(err) = procdata::TintProcessor::new(rpth)
+        .set_profile(profile)
+        .set_backend(params.get_one::<String>("backend").unwrap().to_string())
+        .check()
+    {
+        log::error!("{}", err);
+        process::exit(exitcode::DATAERR);
+    }
+}
+
 /// Main
 fn main() -> Result<(), std::io::Error> {
     let default_empty = String::from("");
@@ -119,7 +171,8 @@ fn main() -> Result<(), std::io::Error> {
         return Ok(());
     }
 
-    if *params.get_one::<bool>("delta-only").unwrap() {
+    // Delta-only is currently disabled in CLI definition
+    if matches!(params.try_get_one::<bool>("delta-only"), Ok(Some(true))) {
         if sys_info::os_type().unwrap_or_default().to_lowercase() != "linux" {
             return Err(std::io::Error::new(std::io::ErrorKind::Unsupported, "Unsupported OS. Only Linux for now..."));
         }
@@ -139,6 +192,13 @@ fn main() -> Result<(), std::io::Error> {
         return Err(std::io::Error::new(std::io::ErrorKind::InvalidData, err.to_string()));
     }
 
+    if let Some(("profile", cmd)) = params.subcommand() {
+        if let Some(("check", params)) = cmd.subcommand() {
+            check_profile(params);
+        }
+        return Ok(());
+    }
+
     // Start data processor
     let rpth = PathBuf::from(params.get_one::<String>("root").unwrap());
     if !rpth.exists() {
@@ -148,10 +208,11 @@ fn main() -> Result<(), std::io::Error> {
 
     log::info!("Launching scanner and data processor");
 
-    if let Err(err) = procdata::TintProcessor::new(rpth)
-        .set_profile(get_profile(cli, &params))
+    if let Err(err) = procdata::TintProcessor::new(rpth.to_owned())
+        .set_profile(get_profile(cli, &params, &rpth))
         .set_dry_run(params.get_flag("dry-run"))
         .set_autodeps(params.get_one::<String>("autodeps").unwrap().to_string())
+        .set_backend(params.get_one::<String>("backend").unwrap().to_string())
         .copy_to(params.get_one::<String>("copy").unwrap_or(&default_empty))?
         .start()
     {
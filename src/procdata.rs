@@ -1,15 +1,31 @@
 use crate::{
     filters::{dirs::PathsDataFilter, intf::DataFilter, resources::ResourcesDataFilter, texts::TextDataFilter},
-    profile::Profile,
+    profile::{LibsMode, PkgDbMode, Profile},
     rootfs::{self, RootFS},
-    scanner::{binlib::ElfScanner, debpkg::DebPackageScanner, dlst::ContentFormatter, general::Scanner},
+    scanner::{
+        backend::{self, PackageBackend},
+        binlib::ElfScanner,
+        dlscan::DlopenScanner,
+        dlst::ContentFormatter,
+        elfarch::ElfArch,
+        general::Scanner,
+        glibc::GlibcDeps,
+        ldresolv::LdResolver,
+        pldeps::PerlScanner,
+        pydeps::PythonScanner,
+        shdeps::ShellScanner,
+        shebang::Shebang,
+        symusage::SymbolUsage,
+        verchk::VersionCheck,
+    },
     shcall::ShellScript,
 };
 use chrono::Local;
+use colored::Colorize;
 use flate2::{write::GzEncoder, Compression};
 use log::info;
 use std::{
-    collections::HashSet,
+    collections::{HashMap, HashSet},
     fs::{self, canonicalize, remove_file, DirEntry, File},
     io::{Error, ErrorKind},
     os::unix,
@@ -32,6 +48,7 @@ pub struct TintProcessor {
     root: PathBuf,
     dry_run: bool,
     autodeps: Autodeps,
+    backend: String,
     lockfile: PathBuf,
     copy_to: Option<PathBuf>, // do not erase unneeded, but instead extract content into an archive
 }
@@ -43,6 +60,7 @@ impl TintProcessor {
             root,
             dry_run: true,
             autodeps: Autodeps::Free,
+            backend: "auto".to_string(),
             lockfile: PathBuf::from("/.tinted.lock"),
             copy_to: None,
         }
@@ -60,6 +78,12 @@ impl TintProcessor {
         self
     }
 
+    /// Set package backend: "auto" to detect it from the image, or a name of the package manager
+    pub fn set_backend(&mut self, backend: String) -> &mut Self {
+        self.backend = backend;
+        self
+    }
+
     /// Set flag for automatic dependency tracing
     pub fn set_autodeps(&mut self, ad: String) -> &mut Self {
         match ad.as_str() {
@@ -191,20 +215,9 @@ impl TintProcessor {
         Ok(())
     }
 
-    fn ext_path(p: HashSet<PathBuf>, mut np: HashSet<PathBuf>) -> HashSet<PathBuf> {
-        for tgt in p.iter() {
-            if tgt.is_symlink() {
-                let mut n_tgt = fs::read_link(tgt).unwrap();
-                n_tgt = tgt.parent().unwrap().join(&n_tgt);
-
-                if !np.contains(&n_tgt) {
-                    np.insert(n_tgt);
-                    np.extend(TintProcessor::ext_path(p.clone(), np.clone()));
-                }
-            }
-        }
-
-        np
+    /// Get all targets of the symlinks, following the whole chain
+    fn ext_path(p: &HashSet<PathBuf>) -> HashSet<PathBuf> {
+        p.iter().filter(|p| p.is_symlink()).flat_map(|p| RootFS::get_link_chain(p).into_iter().skip(1)).collect()
     }
 
     /// Call a script hook
@@ -225,6 +238,285 @@ impl TintProcessor {
         Ok(())
     }
 
+    /// Get profile targets. Scripts are followed by their interpreters,
+    /// which are then treated as additional targets. Commands of the shell
+    /// scripts are also added, if the shell scanner is enabled.
+    fn get_targets(&self) -> Vec<String> {
+        let mut targets: Vec<String> = Vec::default();
+        let mut queue: Vec<String> = self.profile.get_targets().to_owned();
+        queue.reverse();
+
+        while let Some(t) = queue.pop() {
+            if targets.contains(&t) {
+                continue;
+            }
+            targets.push(t.to_owned());
+
+            for interp in Shebang::chain(Path::new(&t)) {
+                if !interp.exists() {
+                    log::warn!("Interpreter {:?} of {} was not found", interp, t);
+                    continue;
+                }
+
+                let interp = interp.to_str().unwrap_or_default().to_string();
+                if !targets.contains(&interp) {
+                    log::info!("Adding interpreter {} for {}", interp.bright_yellow(), t);
+                    targets.push(interp);
+                }
+            }
+
+            if self.profile.has_scanner(&t, "shell") && ShellScanner::is_shell(Path::new(&t)) {
+                for cmd in ShellScanner::new().scan(PathBuf::from(&t)).get_paths() {
+                    let cmd = cmd.to_str().unwrap_or_default().to_string();
+                    if !targets.contains(&cmd) && !queue.contains(&cmd) {
+                        log::info!("Adding {} for {}", cmd.bright_yellow(), t);
+                        queue.insert(0, cmd);
+                    }
+                }
+            }
+        }
+
+        targets
+    }
+
+    /// Report libraries, those are linked, but are not supplying any symbols.
+    /// Optionally prune the rest of their packages.
+    ///
+    /// NOTE: Libraries themselves are still preserved, because dynamic loader
+    ///       refuses to start a binary, if anything from DT_NEEDED is missing.
+    fn unused_libs(
+        &self, backend: &mut dyn PackageBackend, unused: &HashMap<PathBuf, Vec<PathBuf>>, elf_paths: &HashSet<PathBuf>,
+        paths: &mut HashSet<PathBuf>,
+    ) -> Result<(), Error> {
+        let mut pkgs: HashSet<String> = HashSet::default();
+
+        let mut libs = unused.keys().collect::<Vec<&PathBuf>>();
+        libs.sort();
+
+        for lib in libs {
+            let pkg = backend.get_owner(lib.to_owned());
+            log::warn!(
+                "Library {} ({}) is linked by {}, but supplies no symbols",
+                lib.to_str().unwrap_or_default().bright_yellow(),
+                pkg.to_owned().unwrap_or("no package".to_string()),
+                unused[lib].iter().map(|p| p.to_str().unwrap_or_default().to_string()).collect::<Vec<String>>().join(", ")
+            );
+
+            if let Some(pkg) = pkg {
+                pkgs.insert(pkg);
+            }
+        }
+
+        if self.profile.get_unused_libs() != LibsMode::Enforce {
+            return Ok(());
+        }
+
+        // Package can also contain used libraries, e.g. libm is in the same package as libc
+        let mut used_pkgs: HashSet<String> = HashSet::default();
+        for p in elf_paths.iter().filter(|p| !unused.contains_key(*p)) {
+            if let Some(pkg) = backend.get_owner(p.to_owned()) {
+                used_pkgs.insert(pkg);
+            }
+        }
+
+        for pkg in pkgs {
+            if self.profile.get_packages().contains(&pkg) || used_pkgs.contains(&pkg) {
+                continue;
+            }
+
+            log::info!("Pruning package {} of unused libraries", pkg.bright_yellow());
+            for p in backend.get_contents(pkg)? {
+                for p in RootFS::expand_target(p, true) {
+                    if !elf_paths.contains(&p) {
+                        paths.remove(&p);
+                    }
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Keep configuration files of the kept packages and drop /etc entries of the fully pruned ones,
+    /// unless they are kept explicitly. Locally modified configuration files are never dropped,
+    /// but the explicit prune rules always win.
+    fn conffiles(
+        &self, backend: &mut dyn PackageBackend, scanned: &HashSet<PathBuf>, paths: &mut HashSet<PathBuf>,
+        reasons: &mut HashMap<PathBuf, String>,
+    ) -> Result<(), Error> {
+        let etc = Path::new("/etc");
+
+        // Packages, which are still kept, if not counting their configuration.
+        // Packages of the profile, of the targets and their dependencies are kept,
+        // even if they have nothing but /etc, e.g. netbase.
+        let mut pkgs: HashSet<String> = self.profile.get_packages().iter().cloned().collect();
+        pkgs.extend(backend.get_reasons().keys().cloned());
+        for p in scanned.iter().chain(paths.iter().filter(|p| !p.starts_with(etc))) {
+            if let Some(pkg) = backend.get_owner(p.to_owned()) {
+                pkgs.insert(pkg);
+            }
+        }
+        for pkg in self.profile.get_dropped_packages() {
+            pkgs.remove(pkg);
+        }
+
+        let keep = self.profile.get_keep_paths();
+        let prune = self.profile.get_prune_paths();
+        for p in
+            paths.iter().filter(|p| p.starts_with(etc) && !keep.iter().any(|r| r.matches(p))).cloned().collect::<Vec<PathBuf>>()
+        {
+            if let Some(pkg) = backend.get_owner(p.to_owned()).filter(|pkg| !pkgs.contains(pkg)) {
+                log::debug!("Dropping {:?} of pruned package {}", p, pkg);
+                paths.remove(&p);
+            }
+        }
+
+        let mut conffiles = backend.get_conffiles().into_iter().filter(|(p, _)| p.is_file()).collect::<Vec<_>>();
+        conffiles.sort();
+        for (p, (pkg, sum)) in conffiles {
+            // Checksum is not known until the package is configured
+            if prune.iter().any(|r| r.matches(&p)) && !keep.iter().any(|r| r.matches(&p)) {
+                paths.remove(&p);
+                continue;
+            }
+
+            let modified = sum != "newconffile"
+                && match fs::read(&p) {
+                    Ok(data) => format!("{:x}", md5::compute(data)) != sum,
+                    Err(err) => {
+                        log::warn!("Unable to read configuration file {:?}: {}", p, err);
+                        continue;
+                    }
+                };
+            if pkgs.contains(&pkg) || keep.iter().any(|r| r.matches(&p)) {
+                paths.insert(p.to_owned());
+            } else if modified {
+                log::warn!(
+                    "Configuration file {:?} of pruned package {} is locally modified, keeping it",
+                    p,
+                    pkg.bright_yellow()
+                );
+                paths.insert(p.to_owned());
+            } else {
+                paths.remove(&p);
+                continue;
+            }
+
+            if modified {
+                reasons.insert(p, "modified configuration".to_string());
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Report how many paths an explicit rule has matched
+    fn report_rule(kind: &str, rule: &str, matched: usize) {
+        if matched == 0 {
+            log::warn!("{} rule \"{}\" matches nothing", kind, rule);
+        } else {
+            log::info!("{} rule \"{}\" matches {} paths", kind, rule.bright_yellow(), matched);
+        }
+    }
+
+    /// Get all files of the package database
+    fn get_db_files(backend: &dyn PackageBackend) -> Vec<PathBuf> {
+        backend
+            .get_db_paths()
+            .iter()
+            .flat_map(|d| walkdir::WalkDir::new(d).into_iter().filter_map(Result::ok).map(|e| e.into_path()))
+            .filter(|p| !p.is_dir())
+            .collect()
+    }
+
+    /// Verify that the kept libraries provide all the symbol versions, required by the kept objects
+    fn verify_versions(&self, paths: &HashSet<PathBuf>) -> Result<(), Error> {
+        let mut paths = paths.iter().cloned().collect::<Vec<PathBuf>>();
+        paths.sort();
+
+        let problems = VersionCheck::new(&paths).check();
+        for p in &problems {
+            let msg = match &p.version {
+                Some(v) => {
+                    format!("{:?} requires {} from {}, but no kept library provides it", p.object, v.bright_yellow(), p.soname)
+                }
+                None => format!("{:?} requires {}, but it is not kept", p.object, p.soname.bright_yellow()),
+            };
+
+            if self.profile.get_lib_versions() == LibsMode::Enforce {
+                log::error!("{}", msg);
+            } else {
+                log::warn!("{}", msg);
+            }
+        }
+
+        if !problems.is_empty() && self.profile.get_lib_versions() == LibsMode::Enforce {
+            return Err(Error::new(
+                ErrorKind::InvalidData,
+                format!("{} symbol version requirements are not satisfied", problems.len()),
+            ));
+        }
+
+        Ok(())
+    }
+
+    /// Validate the profile against the root filesystem, without changing anything:
+    /// targets, kept paths and packages must exist in the image.
+    pub fn check(&mut self) -> Result<(), Error> {
+        self.switch_root()?;
+
+        let backend = backend::get_backend(&self.backend, self.autodeps)?;
+        let mut problems: Vec<String> = Vec::default();
+
+        for t in self.profile.get_targets() {
+            if Path::new(t).symlink_metadata().is_err() {
+                problems.push(format!("Target {} does not exist", t));
+            }
+        }
+
+        let rfs = RootFS::new();
+        for r in self.profile.get_keep_paths() {
+            if r.expand(rfs.get_files()).is_empty() {
+                problems.push(format!("Keep rule \"{}\" matches nothing", r.get_rule()));
+            }
+        }
+
+        for r in self.profile.get_prune_paths() {
+            if r.expand(rfs.get_files()).is_empty() {
+                log::warn!("Prune rule \"{}\" matches nothing", r.get_rule());
+            }
+        }
+
+        for (kind, pkgs) in [
+            ("Package", self.profile.get_packages()),
+            ("Dropped package", self.profile.get_dropped_packages()),
+            ("Bundled package", self.profile.get_bundled_packages()),
+        ] {
+            for p in pkgs {
+                if !backend.has_package(p) {
+                    problems.push(format!("{} {} is not installed", kind, p));
+                }
+            }
+        }
+
+        for p in self.profile.get_deps_policy().get_exclude() {
+            if !backend.has_package(p) {
+                log::warn!("Excluded dependency package {} is not installed", p);
+            }
+        }
+
+        for p in &problems {
+            log::error!("{}", p);
+        }
+
+        if !problems.is_empty() {
+            return Err(Error::new(ErrorKind::InvalidData, format!("Profile has {} problems", problems.len())));
+        }
+
+        log::info!("Profile is valid for {} ({})", self.root.to_str().unwrap_or_default().bright_yellow(), backend.get_name());
+        Ok(())
+    }
+
     // Start tint processor
     pub fn start(&mut self) -> Result<(), Error> {
         self.switch_root()?;
@@ -234,6 +526,10 @@ impl TintProcessor {
             return Err(Error::new(ErrorKind::AlreadyExists, "This container seems already tinted."));
         }
 
+        // Package manager of the image, not of the host
+        let mut backend = backend::get_backend(&self.backend, self.autodeps)?;
+        backend.set_deps_policy(self.profile.get_deps_policy().to_owned());
+
         // Run pre-hook, if any
         if self.profile.has_pre_hook() {
             if self.dry_run {
@@ -246,26 +542,106 @@ impl TintProcessor {
         // Paths to keep
         let mut paths: HashSet<PathBuf> = HashSet::default();
 
-        for target_path in self.profile.get_targets() {
+        // Linked, but unused libraries
+        let mut elf_paths: HashSet<PathBuf> = HashSet::default();
+        let mut unused_libs: HashMap<PathBuf, Vec<PathBuf>> = HashMap::default();
+        let mut used_libs: HashSet<PathBuf> = HashSet::default();
+
+        // Packages, those are already scanned for the previous targets
+        let mut scanned_pkgs: HashSet<String> = HashSet::default();
+
+        // Targets and the contents of their packages with the dependencies
+        let mut scanned: HashSet<PathBuf> = HashSet::default();
+
+        // ld.so.conf and ld.so.cache are read once for all the scanners
+        let resolver = LdResolver::new();
+
+        let targets = self.get_targets();
+        for target_path in &targets {
             log::debug!("Find binary dependencies for {target_path}");
-            paths.extend(ElfScanner::new().scan(Path::new(target_path).to_owned()).get_paths().to_owned());
+            let mut elfscan = ElfScanner::new(&resolver);
+            elf_paths.extend(elfscan.scan(Path::new(target_path).to_owned()).get_paths().to_owned());
+
+            if self.profile.get_unused_libs() != LibsMode::Off {
+                let unused = SymbolUsage::new(elfscan.get_objects()).get_unused();
+                for lib in elfscan.get_objects().iter().skip(1) {
+                    if unused.contains(lib) {
+                        unused_libs.entry(lib.to_owned()).or_default().extend(elfscan.get_needed_by(lib));
+                    } else {
+                        used_libs.insert(lib.to_owned());
+                    }
+                }
+            }
 
-            log::debug!("Find package dependencies for {target_path}");
-            // XXX: This will re-scan again and again, if target_path belongs to the same package
-            paths.extend(DebPackageScanner::new(self.autodeps).scan(Path::new(target_path).to_owned()).get_paths().to_owned());
+            if self.profile.has_scanner(target_path, "python") && PythonScanner::is_python(Path::new(target_path)) {
+                log::debug!("Find Python modules for {target_path}");
+                paths.extend(PythonScanner::new(&resolver).scan(Path::new(target_path).to_owned()).get_paths().to_owned());
+            }
+
+            if self.profile.has_scanner(target_path, "perl") && PerlScanner::is_perl(Path::new(target_path)) {
+                log::debug!("Find Perl modules for {target_path}");
+                paths.extend(PerlScanner::new(&resolver).scan(Path::new(target_path).to_owned()).get_paths().to_owned());
+            }
+
+            match backend.get_owner(Path::new(target_path).to_owned()) {
+                Some(pkg) if !scanned_pkgs.insert(pkg.to_owned()) => {
+                    log::debug!("Package {pkg} of {target_path} is already scanned");
+                }
+                _ => {
+                    log::debug!("Find package dependencies for {target_path}");
+                    let found = backend.scan(Path::new(target_path).to_owned());
+                    scanned.extend(found.iter().cloned());
+                    paths.extend(found);
+                }
+            }
 
             // Add the target itself
             paths.insert(Path::new(target_path).to_owned());
+            scanned.insert(Path::new(target_path).to_owned());
+        }
+
+        // Libraries, those glibc loads on its own, for the architecture of the targets
+        let arch = targets.iter().find_map(|t| ElfArch::of(Path::new(t))).or_else(|| ElfArch::detect(Path::new("/")));
+        if let Some(mut glibc) = GlibcDeps::new(&resolver, &elf_paths, arch) {
+            let mut implicit: Vec<PathBuf> = Vec::default();
+            if self.profile.keep_nss() {
+                implicit.extend(glibc.get_nss());
+            }
+            if self.profile.keep_gconv() {
+                let mut objects = elf_paths.to_owned();
+                objects.extend(targets.iter().map(PathBuf::from));
+                implicit.extend(glibc.get_gconv(&objects));
+            }
+            if self.profile.keep_libgcc() {
+                implicit.extend(glibc.get_libgcc());
+            }
+            elf_paths.extend(implicit);
+        }
+
+        // Libraries, loaded with dlopen(), are found only by heuristics and should be reviewed
+        let mut reasons: HashMap<PathBuf, String> = HashMap::default();
+        if self.profile.scan_dlopen() {
+            let mut objects = elf_paths.to_owned();
+            objects.extend(targets.iter().map(PathBuf::from));
+            reasons.extend(DlopenScanner::new(&resolver).scan(&objects).to_owned());
+            elf_paths.extend(reasons.keys().cloned());
+        }
+
+        paths.extend(elf_paths.to_owned());
+
+        // Library might be unused by one target, but used by another
+        unused_libs.retain(|lib, _| !used_libs.contains(lib));
+        if !unused_libs.is_empty() {
+            self.unused_libs(&mut *backend, &unused_libs, &elf_paths, &mut paths)?;
         }
 
         // Scan content of all profile packages (if any)
         // and then let TextDataFilter removes what still should be removed.
         // The idea is to keep parts only relevant to the runtime.
         log::debug!("Adding requested packages");
-        let pscan = DebPackageScanner::new(Autodeps::Undef);
         for p in self.profile.get_packages() {
             log::debug!("Getting content of package \"{}\"", p);
-            paths.extend(pscan.get_package_contents(p.to_string())?);
+            paths.extend(backend.get_contents(p.to_string())?);
         }
 
         log::debug!("Filtering text data");
@@ -274,17 +650,27 @@ impl TintProcessor {
         log::debug!("Filtering directories");
         PathsDataFilter::new(paths.clone().into_iter().collect::<Vec<PathBuf>>(), self.profile.to_owned()).filter(&mut paths);
 
+        // Explicit rules are matched against the files of the rootfs
+        log::debug!("Scanning existing rootfs");
+        let mut rfs = rootfs::RootFS::new();
+
         // Explicitly keep paths
-        // XXX: Support globbing
-        paths.extend(self.profile.get_keep_paths());
+        for r in self.profile.get_keep_paths() {
+            let matched = r.expand(rfs.get_files());
+            Self::report_rule("Keep", r.get_rule(), matched.len());
+            paths.extend(matched);
+        }
 
         // Explicitly knock-out paths
-        // XXX: Support globbing
-        for p in self.profile.get_prune_paths() {
-            paths.remove(&p);
+        for r in self.profile.get_prune_paths() {
+            let matched = r.expand(rfs.get_files());
+            Self::report_rule("Prune", r.get_rule(), matched.len());
+            for p in matched {
+                paths.remove(&p);
+            }
         }
 
-        paths.extend(TintProcessor::ext_path(paths.clone(), HashSet::default()));
+        paths.extend(TintProcessor::ext_path(&paths));
 
         // Remove resources
         log::debug!("Filtering resources");
@@ -295,20 +681,34 @@ impl TintProcessor {
         // XXX: Exclude .so binaries also from the Elf reader?
         for p in self.profile.get_dropped_packages() {
             log::debug!("Removing dropped package contents from \"{}\"", p);
-            for p in pscan.get_package_contents(p.to_string())? {
+            for p in backend.get_contents(p.to_string())? {
                 for p in RootFS::expand_target(p, true) {
                     paths.remove(&p);
                 }
             }
         }
 
-        // Scan rootfs
-        log::debug!("Scanning existing rootfs");
-        let mut p = rootfs::RootFS::new()
-            .keep_pds(true)
-            .keep_tmp(false)
-            .keep_tree(vec![])
-            .dissect(paths.clone().into_iter().collect::<Vec<PathBuf>>());
+        log::debug!("Processing configuration files");
+        self.conffiles(&mut *backend, &scanned, &mut paths, &mut reasons)?;
+
+        // Package database is kept to be updated after the changes, or is removed even if kept
+        match self.profile.get_pkgdb() {
+            PkgDbMode::Update => paths.extend(Self::get_db_files(&*backend)),
+            PkgDbMode::Remove => {
+                for p in Self::get_db_files(&*backend) {
+                    paths.remove(&p);
+                }
+            }
+            PkgDbMode::Off => {}
+        }
+
+        if self.profile.get_lib_versions() != LibsMode::Off {
+            log::debug!("Verifying symbol versions");
+            self.verify_versions(&paths)?;
+        }
+
+        let mut p =
+            rfs.keep_pds(true).keep_tmp(false).keep_tree(vec![]).dissect(paths.clone().into_iter().collect::<Vec<PathBuf>>());
         p.sort();
 
         let mut paths = paths.into_iter().collect::<Vec<PathBuf>>();
@@ -318,7 +718,12 @@ impl TintProcessor {
             if self.profile.has_post_hook() {
                 log::debug!("Post-hook:\n{}", self.profile.get_post_hook());
             }
-            ContentFormatter::new(&paths).set_removed(&p).set_bundled_packages(self.profile.get_bundled_packages()).format();
+            ContentFormatter::new(&paths)
+                .set_removed(&p)
+                .set_bundled_packages(self.profile.get_bundled_packages())
+                .set_reasons(&reasons)
+                .set_backend(&mut *backend)
+                .format();
         } else if self.copy_to.is_some() {
             self.into_archive(&paths)?;
         } else {
@@ -328,8 +733,119 @@ impl TintProcessor {
                 Self::call_script(self.profile.get_post_hook())?;
             }
             self.apply_changes(p)?;
+
+            if self.profile.get_pkgdb() == PkgDbMode::Update {
+                log::debug!("Updating package database");
+                backend.update_db()?;
+            }
         }
 
         Ok(())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::TintProcessor;
+    use crate::{profile::DepsPolicy, profile::Profile, scanner::backend::PackageBackend, variables::Variables};
+    use std::{
+        collections::{HashMap, HashSet},
+        fs,
+        io::Error,
+        path::{Path, PathBuf},
+    };
+
+    /// Backend with the fixed owners and conffiles
+    struct MockBackend {
+        owners: HashMap<PathBuf, String>,
+        reasons: HashMap<String, String>,
+    }
+
+    impl PackageBackend for MockBackend {
+        fn get_name(&self) -> &'static str {
+            "mock"
+        }
+
+        fn scan(&mut self, _target: PathBuf) -> Vec<PathBuf> {
+            Vec::default()
+        }
+
+        fn get_owner(&mut self, path: PathBuf) -> Option<String> {
+            self.owners.get(&path).cloned()
+        }
+
+        fn get_contents(&self, pkgname: String) -> Result<Vec<PathBuf>, Error> {
+            Ok(self.owners.iter().filter(|(_, pkg)| **pkg == pkgname).map(|(p, _)| p.to_owned()).collect())
+        }
+
+        fn has_package(&self, pkgname: &str) -> bool {
+            self.owners.values().any(|pkg| pkg == pkgname)
+        }
+
+        fn get_dependencies(&self, _pkgname: String) -> (Vec<String>, HashMap<String, String>) {
+            (Vec::default(), HashMap::default())
+        }
+
+        fn set_deps_policy(&mut self, _policy: DepsPolicy) {}
+
+        fn get_reasons(&self) -> &HashMap<String, String> {
+            &self.reasons
+        }
+
+        fn get_conffiles(&self) -> HashMap<PathBuf, (String, String)> {
+            self.owners
+                .iter()
+                .map(|(p, pkg)| (p.to_owned(), (pkg.to_owned(), format!("{:x}", md5::compute(fs::read(p).unwrap())))))
+                .collect()
+        }
+
+        fn get_db_paths(&self) -> Vec<PathBuf> {
+            Vec::default()
+        }
+    }
+
+    /// Write configuration files of the packages into a scratch directory
+    fn setup(name: &str, files: &[(&str, &str)]) -> (PathBuf, MockBackend) {
+        let dir = std::env::temp_dir().join(format!("mezzotint-{}-{}", name, std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let mut owners = HashMap::default();
+        for (f, pkg) in files {
+            fs::write(dir.join(f), f).unwrap();
+            owners.insert(dir.join(f), pkg.to_string());
+        }
+        (dir, MockBackend { owners, reasons: HashMap::default() })
+    }
+
+    #[test]
+    fn test_conffiles_of_dependency() {
+        // netbase has nothing but /etc, but is pulled in by Depends
+        let (dir, mut backend) = setup("conffiles-deps", &[("services", "netbase"), ("orphan.conf", "orphan")]);
+        backend.reasons.insert("netbase".to_string(), "Depends of bash".to_string());
+
+        let mut paths = HashSet::default();
+        TintProcessor::new(PathBuf::from("/"))
+            .conffiles(&mut backend, &HashSet::default(), &mut paths, &mut HashMap::default())
+            .unwrap();
+        fs::remove_dir_all(&dir).unwrap();
+
+        assert!(paths.contains(&dir.join("services")));
+        assert!(!paths.contains(&dir.join("orphan.conf")));
+    }
+
+    #[test]
+    fn test_conffiles_explicit_prune() {
+        let (dir, mut backend) = setup("conffiles-prune", &[("bash.bashrc", "bash"), ("inputrc", "bash")]);
+        let pfl = dir.join("profile.yaml");
+        fs::write(&pfl, format!("targets: [/bin/sh]\nconfig:\n  prune:\n    - {}\n", dir.join("bash.bashrc").display())).unwrap();
+        let profile = Profile::new(Path::new(&pfl), &Variables::new()).unwrap();
+
+        let scanned: HashSet<PathBuf> = backend.owners.keys().cloned().collect();
+        let mut paths = HashSet::default();
+        let mut tp = TintProcessor::new(PathBuf::from("/"));
+        tp.set_profile(profile).conffiles(&mut backend, &scanned, &mut paths, &mut HashMap::default()).unwrap();
+        fs::remove_dir_all(&dir).unwrap();
+
+        assert!(!paths.contains(&dir.join("bash.bashrc")));
+        assert!(paths.contains(&dir.join("inputrc")));
+    }
+}
@@ -1,24 +1,144 @@
+use crate::{filters::rules::PathRule, variables::Variables};
 use serde::{Deserialize, Serialize};
-use std::{fmt::Debug, path::PathBuf};
-use std::{fs, io::Error, path::Path};
+use serde_yaml::Value;
+use std::fmt::Debug;
+use std::{
+    collections::HashMap,
+    fs,
+    io::{Error, ErrorKind},
+    path::{Path, PathBuf},
+};
 
 #[derive(Serialize, Deserialize, PartialEq, Debug)]
+#[serde(deny_unknown_fields)]
 pub struct PConfig {
     filters: Option<Vec<String>>,
     prune: Option<Vec<String>>,
     keep: Option<Vec<String>>,
+    libs: Option<PLibs>,
+    scanners: Option<Vec<String>>,
+    deps: Option<PDeps>,
+    pkgdb: Option<String>,
 }
 
 #[derive(Serialize, Deserialize, PartialEq, Debug)]
+#[serde(deny_unknown_fields)]
+pub struct PLibs {
+    unused: Option<String>,
+    versions: Option<String>,
+    nss: Option<bool>,
+    gconv: Option<bool>,
+    libgcc: Option<bool>,
+    dlopen: Option<bool>,
+}
+
+#[derive(Serialize, Deserialize, PartialEq, Debug)]
+#[serde(deny_unknown_fields)]
+pub struct PDeps {
+    relations: Option<Vec<String>>,
+    depth: Option<usize>,
+    exclude: Option<Vec<String>>,
+}
+
+/// Language scanners, as they are named in the profile
+pub const SCANNERS: &[&str] = &["python", "perl", "shell"];
+
+/// Relation types of the package dependencies, as they are named in the profile
+pub const DEPS_RELATIONS: &[&str] = &["pre-depends", "depends", "recommends", "suggests"];
+
+/// Policy of the automatic package dependencies tracing
+#[derive(Clone, Debug)]
+pub struct DepsPolicy {
+    relations: Vec<String>,
+    depth: Option<usize>,
+    exclude: Vec<String>,
+}
+
+impl DepsPolicy {
+    /// Only the hard dependencies are followed by default, without any depth limit
+    pub fn default() -> Self {
+        DepsPolicy { relations: vec!["pre-depends".to_string(), "depends".to_string()], depth: None, exclude: vec![] }
+    }
+
+    /// Returns true if the relation type (e.g. "Recommends") needs to be followed
+    pub fn has_relation(&self, name: &str) -> bool {
+        self.relations.iter().any(|r| r.eq_ignore_ascii_case(name))
+    }
+
+    /// Get maximum depth of the dependency graph, if any.
+    /// Depth 1 are the direct dependencies of the target package.
+    pub fn get_depth(&self) -> Option<usize> {
+        self.depth
+    }
+
+    /// Get packages, those are never pulled in as dependencies
+    pub fn get_exclude(&self) -> &Vec<String> {
+        &self.exclude
+    }
+}
+
+/// What to do with the package database of the image
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum PkgDbMode {
+    Off,
+    Update,
+    Remove,
+}
+
+/// Mode of the shared libraries analysis
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum LibsMode {
+    Off,
+    Report,
+    Enforce,
+}
+
+#[derive(Serialize, Deserialize, PartialEq, Debug)]
+#[serde(deny_unknown_fields)]
 pub struct PTargets {
-    targets: Vec<String>,
+    extends: Option<PFiles>,
+    include: Option<PFiles>,
+    targets: Option<Vec<PTarget>>,
     packages: Option<Vec<String>>,
     bundled_packages: Option<Vec<String>>,
     config: Option<PConfig>,
     hooks: Option<PHooks>,
 }
 
+/// Target: a path, or a path with its own language scanners
 #[derive(Serialize, Deserialize, PartialEq, Debug)]
+#[serde(untagged, expecting = "a path or a mapping with \"path\" and \"scanners\"")]
+pub enum PTarget {
+    Path(String),
+    Target(PTargetEntry),
+}
+
+#[derive(Serialize, Deserialize, PartialEq, Debug)]
+#[serde(deny_unknown_fields)]
+pub struct PTargetEntry {
+    path: String,
+    scanners: Option<Vec<String>>,
+}
+
+/// Parent profiles or fragments: one path or a list of them
+#[derive(Serialize, Deserialize, PartialEq, Debug)]
+#[serde(untagged)]
+pub enum PFiles {
+    One(String),
+    Many(Vec<String>),
+}
+
+impl PFiles {
+    fn get_paths(&self) -> Vec<String> {
+        match self {
+            PFiles::One(p) => vec![p.to_owned()],
+            PFiles::Many(p) => p.to_owned(),
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, PartialEq, Debug)]
+#[serde(deny_unknown_fields)]
 pub struct PHooks {
     before: Option<String>,
     after: Option<String>,
@@ -35,8 +155,26 @@ pub struct Profile {
     f_log: bool,
     f_img: bool,
     f_arc: bool,
-    f_expl_prune: Vec<PathBuf>,
-    f_expl_keep: Vec<PathBuf>,
+    f_expl_prune: Vec<PathRule>,
+    f_expl_keep: Vec<PathRule>,
+
+    // shared libraries analysis
+    l_unused: LibsMode,
+    l_versions: LibsMode,
+    l_nss: bool,
+    l_gconv: bool,
+    l_libgcc: bool,
+    l_dlopen: bool,
+
+    // language scanners, for all targets and per target
+    scanners: Vec<String>,
+    target_scanners: HashMap<String, Vec<String>>,
+
+    // package dependencies
+    deps: DepsPolicy,
+
+    // package database
+    pkgdb: PkgDbMode,
 
     packages: Vec<String>,
     bundled_packages: Vec<String>,
@@ -69,24 +207,156 @@ impl Profile {
             f_expl_prune: vec![],
             f_expl_keep: vec![],
 
+            l_unused: LibsMode::Off,
+            l_versions: LibsMode::Off,
+            l_nss: true,
+            l_gconv: true,
+            l_libgcc: true,
+            l_dlopen: false,
+
+            scanners: vec![],
+            target_scanners: HashMap::default(),
+
+            deps: DepsPolicy::default(),
+            pkgdb: PkgDbMode::Off,
+
             s_post: String::from(""),
             s_pre: String::from(""),
         }
     }
 
-    /// Constructor for the Profile. By default all filters are set to OFF
-    pub fn new(pfl_path: &Path) -> Result<Self, Error> {
+    /// Constructor for the Profile. By default all filters are set to OFF.
+    /// Variables are substituted in the profile strings.
+    pub fn new(pfl_path: &Path, vars: &Variables) -> Result<Self, Error> {
         let mut p = Profile::default();
-        match p.parse_profile(pfl_path) {
+        match p.parse_profile(pfl_path, vars) {
             Ok(_) => Ok(p),
             Err(err) => Err(err),
         }
     }
 
-    fn parse_profile(&mut self, pfl_path: &Path) -> Result<(), Error> {
-        let p: PTargets = serde_yaml::from_str::<PTargets>(&fs::read_to_string(pfl_path)?).unwrap();
+    /// Error on an unknown value in the profile
+    fn unknown(kind: &str, value: &str) -> Error {
+        Error::new(ErrorKind::InvalidData, format!("Unknown {}: {}", kind, value))
+    }
+
+    /// Load the profile with all its parents and fragments, merged into one document.
+    /// Parents from `extends` are merged first, then fragments from `include` and then the profile itself.
+    /// Relative paths are resolved from the directory of the profile.
+    fn load(pfl_path: &Path, vars: &Variables, chain: &mut Vec<PathBuf>) -> Result<Value, Error> {
+        let pfl_path = fs::canonicalize(pfl_path)
+            .map_err(|err| Error::new(err.kind(), format!("Unable to open profile {}: {}", pfl_path.display(), err)))?;
+        if chain.contains(&pfl_path) {
+            chain.push(pfl_path);
+            return Err(Error::new(
+                ErrorKind::InvalidData,
+                format!(
+                    "Cyclic profile inheritance: {}",
+                    chain.iter().map(|p| p.display().to_string()).collect::<Vec<String>>().join(" -> ")
+                ),
+            ));
+        }
+
+        // Every file is validated on its own. Errors of serde_yaml are already pointing to the line and column.
+        let data = fs::read_to_string(&pfl_path)?;
+        let invalid = |err: serde_yaml::Error| {
+            Error::new(ErrorKind::InvalidData, format!("Invalid profile {}: {}", pfl_path.display(), err))
+        };
+        let p = serde_yaml::from_str::<PTargets>(&data).map_err(invalid)?;
+        let mut doc = serde_yaml::from_str::<Value>(&data).map_err(invalid)?;
+        if let Value::Mapping(m) = &mut doc {
+            m.remove("extends");
+            m.remove("include");
+        }
+
+        let dir = pfl_path.parent().unwrap_or(Path::new("/")).to_owned();
+        let mut base = Value::Null;
+        chain.push(pfl_path);
+        for (kind, files) in [("Extending", p.extends), ("Including", p.include)] {
+            for f in files.map(|f| f.get_paths()).unwrap_or_default() {
+                log::debug!("{} profile with {}", kind, f);
+                base = Self::merge(base, Self::load(&dir.join(vars.expand(&f)?), vars, chain)?, "");
+            }
+        }
+        chain.pop();
+
+        Ok(Self::merge(base, doc, ""))
+    }
+
+    /// Merge the document over the base: mappings are merged recursively,
+    /// lists are unioned and scalars are overridden.
+    fn merge(base: Value, over: Value, key: &str) -> Value {
+        match (base, over) {
+            (base, Value::Null) => base,
+            (Value::Mapping(mut base), Value::Mapping(over)) => {
+                for (k, v) in over {
+                    let name = k.as_str().unwrap_or_default().to_string();
+                    match base.get_mut(&k) {
+                        Some(b) => *b = Self::merge(std::mem::take(b), v, &name),
+                        None => {
+                            base.insert(k, v);
+                        }
+                    }
+                }
+                Value::Mapping(base)
+            }
+            (Value::Sequence(base), Value::Sequence(over)) => Value::Sequence(Self::merge_list(base, over, key == "packages")),
+            (_, over) => over,
+        }
+    }
+
+    /// Union of the lists. In all lists "-foo" removes the inherited "foo" and is not kept itself.
+    /// In packages it also removes "+foo", and it is kept as a drop of "foo" only
+    /// if nothing is inherited. Otherwise the later entry of the same package wins.
+    fn merge_list(mut base: Vec<Value>, over: Vec<Value>, packages: bool) -> Vec<Value> {
+        // Targets with their own scanners are identified by the path
+        let entry = |v: &Value| v.get("path").unwrap_or(v).as_str().map(|s| s.replace(' ', "")).unwrap_or_default();
+        let name = |v: &Value| entry(v).trim_start_matches(['+', '-']).to_string();
+
+        for v in over {
+            if packages {
+                let inherited = base.iter().any(|b| name(b) == name(&v) && !entry(b).starts_with('-'));
+                base.retain(|b| name(b) != name(&v));
+                if !(inherited && entry(&v).starts_with('-')) {
+                    base.push(v);
+                }
+            } else if let Some(rm) = entry(&v).strip_prefix('-') {
+                base.retain(|b| entry(b) != rm);
+            } else if !base.contains(&v) {
+                base.push(v);
+            }
+        }
+
+        base
+    }
+
+    /// Substitute variables in all strings of the document, except the hooks,
+    /// those are shell scripts with their own variables.
+    fn substitute(doc: Value, vars: &Variables, key: &str) -> Result<Value, Error> {
+        Ok(match doc {
+            Value::String(s) if key != "hooks" => Value::String(vars.expand(&s)?),
+            Value::Sequence(seq) => {
+                Value::Sequence(seq.into_iter().map(|v| Self::substitute(v, vars, key)).collect::<Result<_, _>>()?)
+            }
+            Value::Mapping(m) => {
+                let mut out = serde_yaml::Mapping::default();
+                for (k, v) in m {
+                    let v = Self::substitute(v, vars, if key.is_empty() { k.as_str().unwrap_or_default() } else { key })?;
+                    out.insert(k, v);
+                }
+                Value::Mapping(out)
+            }
+            doc => doc,
+        })
+    }
+
+    fn parse_profile(&mut self, pfl_path: &Path, vars: &Variables) -> Result<(), Error> {
+        let doc = Self::substitute(Self::load(pfl_path, vars, &mut vec![])?, vars, "")
+            .map_err(|err| Error::new(err.kind(), format!("Invalid profile {}: {}", pfl_path.display(), err)))?;
+        log::debug!("Effective profile {}:\n{}", pfl_path.display(), serde_yaml::to_string(&doc).unwrap_or_default().trim_end());
 
-        log::trace!("{:?}", p);
+        let p: PTargets = serde_yaml::from_value(doc)
+            .map_err(|err| Error::new(ErrorKind::InvalidData, format!("Invalid profile {}: {}", pfl_path.display(), err)))?;
 
         if let Some(cfg) = p.config {
             if let Some(af) = cfg.filters {
@@ -113,22 +383,110 @@ impl Profile {
                             self.f_arc = false;
                         }
                         unknown => {
-                            log::warn!("Unknown filter: {}", unknown);
+                            return Err(Self::unknown("filter", unknown));
                         }
                     }
                 }
             }
 
             if let Some(prn) = cfg.prune {
-                self.f_expl_prune.extend(prn.iter().map(PathBuf::from).collect::<Vec<PathBuf>>());
+                for r in prn {
+                    self.f_expl_prune.push(PathRule::new(&r)?);
+                }
             }
 
             if let Some(keep) = cfg.keep {
-                self.f_expl_keep.extend(keep.iter().map(PathBuf::from).collect::<Vec<PathBuf>>());
+                for r in keep {
+                    self.f_expl_keep.push(PathRule::new(&r)?);
+                }
+            }
+
+            if let Some(libs) = cfg.libs {
+                if let Some(unused) = libs.unused {
+                    match unused.as_str() {
+                        "report" => self.l_unused = LibsMode::Report,
+                        "prune-packages" => self.l_unused = LibsMode::Enforce,
+                        unknown => {
+                            return Err(Self::unknown("mode for unused libraries", unknown));
+                        }
+                    }
+                }
+
+                if let Some(versions) = libs.versions {
+                    match versions.as_str() {
+                        "report" => self.l_versions = LibsMode::Report,
+                        "strict" => self.l_versions = LibsMode::Enforce,
+                        unknown => {
+                            return Err(Self::unknown("mode for symbol versions verification", unknown));
+                        }
+                    }
+                }
+
+                // Implicit glibc dependencies are kept by default
+                self.l_nss = libs.nss.unwrap_or(true);
+                self.l_gconv = libs.gconv.unwrap_or(true);
+                self.l_libgcc = libs.libgcc.unwrap_or(true);
+
+                // Heuristics are opt-in
+                self.l_dlopen = libs.dlopen.unwrap_or(false);
+            }
+
+            if let Some(scanners) = cfg.scanners {
+                for s in scanners {
+                    if !SCANNERS.contains(&s.as_str()) {
+                        return Err(Self::unknown("scanner", &s));
+                    }
+                    self.add_scanner(s);
+                }
+            }
+
+            if let Some(deps) = cfg.deps {
+                if let Some(relations) = deps.relations {
+                    self.deps.relations.clear();
+                    for r in relations {
+                        let r = r.to_lowercase();
+                        if !DEPS_RELATIONS.contains(&r.as_str()) {
+                            return Err(Self::unknown("dependency relation", &r));
+                        } else if !self.deps.relations.contains(&r) {
+                            self.deps.relations.push(r);
+                        }
+                    }
+                }
+
+                self.deps.depth = deps.depth;
+                self.deps.exclude.extend(deps.exclude.unwrap_or_default());
+            }
+
+            if let Some(pkgdb) = cfg.pkgdb {
+                match pkgdb.as_str() {
+                    "update" => self.pkgdb = PkgDbMode::Update,
+                    "remove" => self.pkgdb = PkgDbMode::Remove,
+                    unknown => {
+                        return Err(Self::unknown("mode for package database", unknown));
+                    }
+                }
             }
         }
 
-        self.targets.extend(p.targets);
+        match p.targets {
+            Some(targets) => {
+                for t in targets {
+                    match t {
+                        PTarget::Path(path) => self.targets.push(path),
+                        PTarget::Target(t) => {
+                            for s in t.scanners.unwrap_or_default() {
+                                if !SCANNERS.contains(&s.as_str()) {
+                                    return Err(Self::unknown("scanner", &s));
+                                }
+                                self.add_target_scanner(&t.path, s);
+                            }
+                            self.targets.push(t.path);
+                        }
+                    }
+                }
+            }
+            None => return Err(Error::new(ErrorKind::InvalidData, format!("Profile {} has no targets", pfl_path.display()))),
+        }
 
         if let Some(pkgs) = p.packages {
             for p in &pkgs {
@@ -173,6 +531,30 @@ impl Profile {
         self
     }
 
+    /// Enable language scanner
+    pub fn add_scanner(&mut self, name: String) -> &mut Self {
+        match name.as_str() {
+            n if SCANNERS.contains(&n) => {
+                if !self.scanners.contains(&name) {
+                    self.scanners.push(name);
+                }
+            }
+            unknown => {
+                log::warn!("Unknown scanner: {}", unknown);
+            }
+        }
+        self
+    }
+
+    /// Enable language scanner only for the target
+    pub fn add_target_scanner(&mut self, target: &str, name: String) -> &mut Self {
+        let scanners = self.target_scanners.entry(target.to_string()).or_default();
+        if !scanners.contains(&name) {
+            scanners.push(name);
+        }
+        self
+    }
+
     /// Set localisation filter
     pub fn set_l10n(&mut self, remove: bool) -> &mut Self {
         self.f_l10n = remove;
@@ -225,18 +607,18 @@ impl Profile {
         self
     }
 
-    /// Add path prune
+    /// Add path prune: a path, a glob or a "re:" regex
     #[allow(dead_code)]
-    pub fn prune_path(&mut self, pth: String) -> &mut Self {
-        self.f_expl_prune.push(PathBuf::from(pth));
-        self
+    pub fn prune_path(&mut self, pth: String) -> Result<&mut Self, Error> {
+        self.f_expl_prune.push(PathRule::new(&pth)?);
+        Ok(self)
     }
 
-    /// Add path to be kept
+    /// Add path to be kept: a path, a glob or a "re:" regex
     #[allow(dead_code)]
-    pub fn keep_path(&mut self, pth: String) -> &mut Self {
-        self.f_expl_keep.push(PathBuf::from(pth));
-        self
+    pub fn keep_path(&mut self, pth: String) -> Result<&mut Self, Error> {
+        self.f_expl_keep.push(PathRule::new(&pth)?);
+        Ok(self)
     }
 
     /// Get targets
@@ -244,14 +626,14 @@ impl Profile {
         &self.targets
     }
 
-    /// Get paths to be explicitly pruned
-    pub fn get_prune_paths(&self) -> Vec<PathBuf> {
-        self.f_expl_prune.clone()
+    /// Get rules of the paths to be explicitly pruned
+    pub fn get_prune_paths(&self) -> &[PathRule] {
+        &self.f_expl_prune
     }
 
-    /// Get paths to be explicitly kept
-    pub fn get_keep_paths(&self) -> Vec<PathBuf> {
-        self.f_expl_keep.clone()
+    /// Get rules of the paths to be explicitly kept
+    pub fn get_keep_paths(&self) -> &[PathRule] {
+        &self.f_expl_keep
     }
 
     /// Returns true if localisation data needs to be removed
@@ -296,6 +678,51 @@ impl Profile {
         !self.f_img
     }
 
+    /// Get mode of linked, but unused libraries analysis
+    pub fn get_unused_libs(&self) -> LibsMode {
+        self.l_unused
+    }
+
+    /// Get mode of symbol versions verification
+    pub fn get_lib_versions(&self) -> LibsMode {
+        self.l_versions
+    }
+
+    /// Returns true if NSS modules of glibc needs to be kept
+    pub fn keep_nss(&self) -> bool {
+        self.l_nss
+    }
+
+    /// Returns true if gconv modules of glibc needs to be kept
+    pub fn keep_gconv(&self) -> bool {
+        self.l_gconv
+    }
+
+    /// Returns true if libgcc_s needs to be kept for glibc
+    pub fn keep_libgcc(&self) -> bool {
+        self.l_libgcc
+    }
+
+    /// Returns true if kept objects needs to be scanned for dlopen() strings
+    pub fn scan_dlopen(&self) -> bool {
+        self.l_dlopen
+    }
+
+    /// Returns true if the language scanner is enabled for the target, or for all targets
+    pub fn has_scanner(&self, target: &str, name: &str) -> bool {
+        self.scanners.iter().chain(self.target_scanners.get(target).into_iter().flatten()).any(|s| s == name)
+    }
+
+    /// Get policy of the package dependencies tracing
+    pub fn get_deps_policy(&self) -> &DepsPolicy {
+        &self.deps
+    }
+
+    /// Get mode of the package database update
+    pub fn get_pkgdb(&self) -> PkgDbMode {
+        self.pkgdb
+    }
+
     /// Get packages
     pub fn get_packages(&self) -> &Vec<String> {
         &self.packages
@@ -331,3 +758,78 @@ impl Profile {
         self.s_post.to_owned()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::Profile;
+    use crate::variables::Variables;
+    use serde_yaml::Value;
+    use std::fs;
+
+    fn yaml(s: &str) -> Value {
+        serde_yaml::from_str(s).unwrap()
+    }
+
+    #[test]
+    fn test_merge_list_union() {
+        let merged = Profile::merge(yaml("keep: [/etc/a, /etc/b]"), yaml("keep: [/etc/b, /etc/c]"), "");
+        assert_eq!(merged, yaml("keep: [/etc/a, /etc/b, /etc/c]"));
+    }
+
+    #[test]
+    fn test_merge_list_subtract() {
+        // "-foo" removes the inherited entry and is not kept itself
+        let merged = Profile::merge(yaml("targets: [/usr/bin/a, /usr/bin/b]"), yaml("targets: [-/usr/bin/a]"), "");
+        assert_eq!(merged, yaml("targets: [/usr/bin/b]"));
+
+        let merged = Profile::merge(yaml("keep: [/etc/a]"), yaml("keep: [-/etc/x]"), "");
+        assert_eq!(merged, yaml("keep: [/etc/a]"));
+    }
+
+    #[test]
+    fn test_merge_packages() {
+        // "-pkg" removes the inherited "pkg" or "+pkg"
+        let merged = Profile::merge(yaml("packages: [bash, +grep, sed]"), yaml("packages: [-bash, -grep]"), "");
+        assert_eq!(merged, yaml("packages: [sed]"));
+
+        // ...and is a drop, if nothing is inherited
+        let merged = Profile::merge(yaml("packages: [bash, -vim]"), yaml("packages: [-vim, -grep]"), "");
+        assert_eq!(merged, yaml("packages: [bash, -vim, -grep]"));
+
+        // The later entry of the same package wins
+        let merged = Profile::merge(yaml("packages: [-bash, grep]"), yaml("packages: [bash, +grep]"), "");
+        assert_eq!(merged, yaml("packages: [bash, +grep]"));
+    }
+
+    #[test]
+    fn test_merge_scalars() {
+        // Mappings are merged recursively, scalars are overridden
+        let merged = Profile::merge(
+            yaml("config: {libs: {unused: report}, deps: {depth: 1}, pkgdb: remove}"),
+            yaml("config: {libs: {unused: prune-packages}, pkgdb: update}"),
+            "",
+        );
+        assert_eq!(merged, yaml("config: {libs: {unused: prune-packages}, deps: {depth: 1}, pkgdb: update}"));
+    }
+
+    #[test]
+    fn test_load_inheritance() {
+        let dir = std::env::temp_dir().join(format!("mezzotint-profiles-{}", std::process::id()));
+        fs::create_dir_all(dir.join("fragments")).unwrap();
+        fs::write(dir.join("base.yaml"), "targets: [/usr/bin/bash]\npackages: [grep]\n").unwrap();
+        fs::write(dir.join("fragments/keep.yaml"), "config: {keep: [/etc/hosts]}\n").unwrap();
+        fs::write(dir.join("app.yaml"), "extends: base.yaml\ninclude: [fragments/keep.yaml]\npackages: [-grep, sed]\n").unwrap();
+        fs::write(dir.join("a.yaml"), "extends: b.yaml\n").unwrap();
+        fs::write(dir.join("b.yaml"), "include: a.yaml\ntargets: [/usr/bin/bash]\n").unwrap();
+
+        let vars = Variables::new();
+        let merged = Profile::load(&dir.join("app.yaml"), &vars, &mut Vec::default());
+        let cyclic = Profile::load(&dir.join("a.yaml"), &vars, &mut Vec::default());
+        fs::remove_dir_all(&dir).unwrap();
+
+        assert_eq!(merged.unwrap(), yaml("targets: [/usr/bin/bash]\npackages: [sed]\nconfig: {keep: [/etc/hosts]}"));
+        let err = cyclic.unwrap_err().to_string();
+        assert!(err.starts_with("Cyclic profile inheritance:"), "{}", err);
+        assert!(err.ends_with("a.yaml"), "{}", err);
+    }
+}
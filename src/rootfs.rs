@@ -1,7 +1,7 @@
 use std::{
     collections::{HashMap, HashSet},
     fs,
-    path::{Path, PathBuf},
+    path::{Component, Path, PathBuf},
     vec,
 };
 
@@ -9,14 +9,21 @@ pub struct RootFS {
     pds: bool, // Keep /proc, /sys and /dev
     tmp: bool, // keep /tmp
     rootfs: HashSet<PathBuf>,
+    files: HashSet<PathBuf>,
     ptree: Vec<PathBuf>,
     broken_links: HashSet<PathBuf>,
 }
 
 impl RootFS {
     pub fn new() -> Self {
-        let mut rf =
-            RootFS { pds: true, tmp: true, rootfs: HashSet::default(), broken_links: HashSet::default(), ptree: Vec::default() };
+        let mut rf = RootFS {
+            pds: true,
+            tmp: true,
+            rootfs: HashSet::default(),
+            files: HashSet::default(),
+            broken_links: HashSet::default(),
+            ptree: Vec::default(),
+        };
         rf.scan();
 
         rf
@@ -39,15 +46,14 @@ impl RootFS {
         self
     }
 
+    /// Get all files of the rootfs, as they are seen through the directories, including symlinks
+    pub fn get_files(&self) -> &HashSet<PathBuf> {
+        &self.files
+    }
+
     /// Get a list what needs to be deleted from the image
     pub fn dissect(&mut self, src: Vec<PathBuf>) -> Vec<PathBuf> {
-        let mut rfs: HashSet<PathBuf> = HashSet::default();
-        for p in &self.rootfs {
-            // Don't throw away ld-linux :)
-            if !p.is_file() || !p.file_name().unwrap().to_str().unwrap().starts_with("ld-linux-") {
-                rfs.insert(p.to_owned());
-            }
-        }
+        let mut rfs: HashSet<PathBuf> = self.rootfs.to_owned();
 
         for x in src {
             for y in Self::expand_target(x, false) {
@@ -59,6 +65,47 @@ impl RootFS {
         rfs.into_iter().collect::<Vec<PathBuf>>()
     }
 
+    /// Resolve "." and ".." of the path lexically, for the paths, those are not on the disk
+    fn normalise(p: &Path) -> PathBuf {
+        let mut out = PathBuf::from("/");
+        for c in p.components() {
+            match c {
+                Component::ParentDir => {
+                    out.pop();
+                }
+                Component::Normal(c) => out.push(c),
+                _ => {}
+            }
+        }
+
+        out
+    }
+
+    /// Follow the chain of symlinks, e.g. the ones of update-alternatives:
+    /// "/usr/bin/editor" to "/etc/alternatives/editor" to "/usr/bin/vim.basic".
+    /// Returns the path itself, followed by all the link targets.
+    pub fn get_link_chain(target: &Path) -> Vec<PathBuf> {
+        let mut chain: Vec<PathBuf> = vec![target.to_path_buf()];
+        while let Some(lnk) = chain.last().and_then(|p| p.read_link().ok()) {
+            // Relative target is relative to the real directory of the link, e.g. "../lib" from /lib,
+            // which is a symlink to usr/lib. Its own directories might be symlinks too.
+            let dir = chain.last().unwrap().parent().unwrap_or(Path::new("/"));
+            let lnk = fs::canonicalize(dir).unwrap_or(dir.to_path_buf()).join(lnk);
+            let next = match (lnk.parent().and_then(|d| fs::canonicalize(d).ok()), lnk.file_name()) {
+                (Some(d), Some(n)) => d.join(n),
+                _ => Self::normalise(&lnk),
+            };
+
+            // Symlink loop
+            if chain.contains(&next) {
+                break;
+            }
+            chain.push(next);
+        }
+
+        chain
+    }
+
     /// Expands target taking to the account Linux /bin symlinks to /usr/bin etc.
     ///
     /// This is needed as dpkg won't always find the corresponding package, because
@@ -126,6 +173,7 @@ impl RootFS {
                     }
 
                     if p.is_file() && p.exists() {
+                        self.files.insert(p.to_owned());
                         if let Ok(p) = fs::canonicalize(p) {
                             self.rootfs.insert(p);
                         }
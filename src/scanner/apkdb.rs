@@ -0,0 +1,169 @@
+/*
+Alpine apk database reader.
+
+The installed database is a text file with blank line separated
+records of "X:value" lines. Only the fields, needed for the ownership,
+contents and dependencies, are taken.
+ */
+
+use super::pkgdb::{DbPackage, PackageDb};
+use std::{
+    fs,
+    io::Error,
+    path::{Path, PathBuf},
+};
+
+/// Database of the installed packages
+const APK_INSTALLED: &str = "/lib/apk/db/installed";
+
+/// Installed package, as recorded in the apk database
+#[derive(Default)]
+pub struct ApkPackage {
+    name: String,
+    files: Vec<PathBuf>,
+    depends: Vec<String>,
+    provides: Vec<String>,
+}
+
+impl DbPackage for ApkPackage {
+    fn get_name(&self) -> &str {
+        &self.name
+    }
+
+    fn get_files(&self) -> &[PathBuf] {
+        &self.files
+    }
+
+    fn get_provides(&self) -> &[String] {
+        &self.provides
+    }
+
+    /// Dependencies are package names, "so:" sonames, "cmd:" commands or file paths.
+    /// apk has no weak dependencies.
+    fn get_deps(&self) -> Vec<(&'static str, &'static str, &[String])> {
+        vec![("Depends", "depends", &self.depends)]
+    }
+}
+
+pub struct ApkDb {
+    packages: Vec<ApkPackage>,
+}
+
+impl ApkDb {
+    /// Returns true if apk is the package manager of the image
+    pub fn is_system_db() -> bool {
+        Path::new(APK_INSTALLED).is_file()
+    }
+
+    /// Strip version constraint from a dependency or a provide, e.g. "so:libz.so.1=1.3" or "musl>=1.2"
+    fn strip_version(s: &str) -> &str {
+        s.split(['<', '>', '=', '~']).next().unwrap_or_default()
+    }
+
+    /// Parse records of the installed database
+    fn parse(data: &str) -> Vec<ApkPackage> {
+        let mut packages: Vec<ApkPackage> = Vec::default();
+        let mut pkg = ApkPackage::default();
+        let mut dir = PathBuf::from("/");
+
+        for l in data.lines().chain([""]) {
+            if l.is_empty() {
+                if !pkg.name.is_empty() {
+                    packages.push(pkg);
+                }
+                pkg = ApkPackage::default();
+                dir = PathBuf::from("/");
+                continue;
+            }
+
+            let (key, val) = match l.split_once(':') {
+                Some(kv) => kv,
+                None => continue,
+            };
+
+            match key {
+                "P" => pkg.name = val.to_string(),
+                "F" => dir = Path::new("/").join(val),
+                "R" => pkg.files.push(dir.join(val)),
+                // Conflicts start with "!"
+                "D" => pkg
+                    .depends
+                    .extend(val.split_whitespace().filter(|d| !d.starts_with('!')).map(|d| Self::strip_version(d).to_string())),
+                "p" => pkg.provides.extend(val.split_whitespace().map(|p| Self::strip_version(p).to_string())),
+                _ => {}
+            }
+        }
+
+        packages
+    }
+}
+
+impl PackageDb for ApkDb {
+    type Package = ApkPackage;
+    const NAME: &'static str = "apk";
+
+    /// Read all installed packages
+    fn read() -> Result<Self, Error> {
+        let packages = Self::parse(&fs::read_to_string(APK_INSTALLED)?);
+        log::debug!("Found {} apk packages", packages.len());
+        Ok(ApkDb { packages })
+    }
+
+    fn get_db_paths() -> Vec<PathBuf> {
+        vec![Path::new(APK_INSTALLED).parent().unwrap_or(Path::new("/")).to_path_buf()]
+    }
+
+    fn get_packages(&self) -> &[ApkPackage] {
+        &self.packages
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::ApkDb;
+    use crate::scanner::pkgdb::DbPackage;
+    use std::path::PathBuf;
+
+    const INSTALLED: &str = "\
+C:Q1abc=
+P:busybox
+V:1.36.1-r15
+D:so:libc.musl-x86_64.so.1 !busybox-static
+p:cmd:sh=1.36.1-r15 /bin/sh
+F:bin
+R:busybox
+F:etc
+R:securetty
+
+P:musl
+V:1.2.4-r2
+p:so:libc.musl-x86_64.so.1=1
+F:lib
+R:ld-musl-x86_64.so.1
+R:libc.musl-x86_64.so.1
+";
+
+    #[test]
+    fn test_parse() {
+        let packages = ApkDb::parse(INSTALLED);
+        assert_eq!(packages.iter().map(|p| p.get_name()).collect::<Vec<&str>>(), vec!["busybox", "musl"]);
+
+        let busybox = &packages[0];
+        assert_eq!(busybox.get_files(), &[PathBuf::from("/bin/busybox"), PathBuf::from("/etc/securetty")]);
+        assert_eq!(busybox.get_provides(), &["cmd:sh", "/bin/sh"]);
+        assert_eq!(busybox.get_deps()[0].2, &["so:libc.musl-x86_64.so.1"]);
+
+        let musl = &packages[1];
+        assert_eq!(musl.get_files(), &[PathBuf::from("/lib/ld-musl-x86_64.so.1"), PathBuf::from("/lib/libc.musl-x86_64.so.1")]);
+        assert_eq!(musl.get_provides(), &["so:libc.musl-x86_64.so.1"]);
+        assert!(musl.get_deps()[0].2.is_empty());
+    }
+
+    #[test]
+    fn test_parse_no_trailing_newline() {
+        // The last record is not terminated with a blank line, records without a name are skipped
+        let packages = ApkDb::parse("V:1.0\n\nP:zlib\nF:lib\nR:libz.so.1");
+        assert_eq!(packages.len(), 1);
+        assert_eq!(packages[0].get_files(), &[PathBuf::from("/lib/libz.so.1")]);
+    }
+}
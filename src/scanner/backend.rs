@@ -0,0 +1,148 @@
+/*
+Package manager backends.
+
+Each supported package manager answers the same questions about
+the packages of the image: ownership, contents, dependencies and sizes.
+The backend is chosen from the os-release of the image itself,
+falling back to the package databases, that are present there.
+ */
+
+use super::{
+    apkdb::ApkDb, dbpkg::DbPackageScanner, debpkg::DebPackageScanner, dpkgstatus::DpkgStatus, general::ScannerResult,
+    pacmandb::PacmanDb, rpmdb::RpmDb,
+};
+use crate::{procdata::Autodeps, profile::DepsPolicy};
+use std::{
+    collections::HashMap,
+    fs,
+    io::{Error, ErrorKind},
+    path::{Path, PathBuf},
+};
+
+/// Supported backends, as accepted by --backend
+pub const BACKENDS: &[&str] = &["auto", "dpkg", "rpm", "apk", "pacman"];
+
+/// os-release locations, in the order of preference
+const OS_RELEASE: &[&str] = &["/etc/os-release", "/usr/lib/os-release"];
+
+/// Distribution IDs (ID and ID_LIKE of os-release) to the backend names
+const OS_FAMILIES: &[(&str, &[&str])] = &[
+    ("dpkg", &["debian", "ubuntu", "linuxmint", "mint", "raspbian", "devuan", "kali", "pop"]),
+    (
+        "rpm",
+        &["fedora", "rhel", "centos", "rocky", "almalinux", "ol", "amzn", "suse", "opensuse", "sles", "mageia", "openmandriva"],
+    ),
+    ("apk", &["alpine", "postmarketos", "wolfi"]),
+    ("pacman", &["arch", "archlinux", "manjaro", "endeavouros", "artix"]),
+];
+
+pub trait PackageBackend {
+    /// Name of the backend, e.g. "dpkg"
+    fn get_name(&self) -> &'static str;
+
+    /// Get contents of the package, to which the target belongs to,
+    /// with the dependency packages, according to the autodeps mode
+    fn scan(&mut self, target: PathBuf) -> Vec<PathBuf>;
+
+    /// Return a package name, to which this file belongs to
+    fn get_owner(&mut self, path: PathBuf) -> Option<String>;
+
+    /// Get installed contents of the package, without directories
+    fn get_contents(&self, pkgname: String) -> Result<Vec<PathBuf>, Error>;
+
+    /// Returns true if the package is installed
+    fn has_package(&self, pkgname: &str) -> bool;
+
+    /// Get all dependency packages of the package, according to the policy,
+    /// with the relations, those pulled them in
+    fn get_dependencies(&self, pkgname: String) -> (Vec<String>, HashMap<String, String>);
+
+    /// Set policy of the dependency tracing: relation types, depth and excluded packages
+    fn set_deps_policy(&mut self, policy: DepsPolicy);
+
+    /// Get relations, those pulled in the dependency packages, e.g. "Depends of bash"
+    fn get_reasons(&self) -> &HashMap<String, String>;
+
+    /// Get configuration files of all installed packages:
+    /// path to the package name and the checksum of the packaged version
+    fn get_conffiles(&self) -> HashMap<PathBuf, (String, String)> {
+        HashMap::default()
+    }
+
+    /// Get locations of the package database in the image
+    fn get_db_paths(&self) -> Vec<PathBuf>;
+
+    /// Update the package database to the files, those are still on the disk
+    fn update_db(&mut self) -> Result<(), Error> {
+        log::warn!("Updating {} package database is not supported", self.get_name());
+        Ok(())
+    }
+
+    /// Returns total size of the packages on the disk
+    fn get_size(&self, pkgnames: &[String]) -> i128 {
+        pkgnames.iter().map(|p| ScannerResult::new(self.get_contents(p.to_string()).unwrap_or_default()).get_size()).sum()
+    }
+}
+
+/// Returns true if the package database of the backend is in the image
+fn has_db(name: &str) -> bool {
+    match name {
+        "dpkg" => DpkgStatus::exists(),
+        "rpm" => RpmDb::find().is_some(),
+        "apk" => ApkDb::is_system_db(),
+        "pacman" => PacmanDb::is_system_db(),
+        _ => false,
+    }
+}
+
+/// Get distribution IDs from os-release of the image under the root: ID first, then ID_LIKE
+pub fn get_os_ids(root: &Path) -> Vec<String> {
+    let data = OS_RELEASE.iter().find_map(|p| fs::read_to_string(root.join(p.trim_start_matches('/'))).ok()).unwrap_or_default();
+    let mut ids: Vec<String> = Vec::default();
+    for key in ["ID", "ID_LIKE"] {
+        for l in data.lines() {
+            if let Some(v) = l.strip_prefix(key).and_then(|v| v.strip_prefix('=')) {
+                ids.extend(v.trim_matches(['"', '\'']).split_whitespace().map(|s| s.to_lowercase()));
+            }
+        }
+    }
+
+    ids
+}
+
+/// Detect the package manager of the image
+fn detect() -> &'static str {
+    let ids = get_os_ids(Path::new("/"));
+    for id in &ids {
+        if let Some((name, _)) = OS_FAMILIES.iter().find(|(_, family)| family.contains(&id.as_str())) {
+            if has_db(name) {
+                log::debug!("Distribution \"{}\" is using {}", id, name);
+                return name;
+            }
+            log::warn!("Distribution \"{}\" should be using {}, but its database was not found", id, name);
+        }
+    }
+
+    // No or unknown os-release, e.g. a custom image: take whatever database is there
+    match ["dpkg", "apk", "pacman", "rpm"].iter().find(|n| has_db(n)) {
+        Some(name) => name,
+        None => {
+            log::warn!("No package database was found in the image, assuming dpkg");
+            "dpkg"
+        }
+    }
+}
+
+/// Get the package backend by its name, or detect it for "auto"
+pub fn get_backend(name: &str, autodeps: Autodeps) -> Result<Box<dyn PackageBackend>, Error> {
+    let backend: Box<dyn PackageBackend> = match if name == "auto" { detect() } else { name } {
+        "dpkg" => Box::new(DebPackageScanner::new(autodeps)),
+        "rpm" => Box::new(DbPackageScanner::<RpmDb>::new(autodeps)),
+        "apk" => Box::new(DbPackageScanner::<ApkDb>::new(autodeps)),
+        "pacman" => Box::new(DbPackageScanner::<PacmanDb>::new(autodeps)),
+        _ => return Err(Error::new(ErrorKind::InvalidInput, format!("Unknown package backend: {}", name))),
+    };
+
+    log::info!("Using {} package backend", backend.get_name());
+    Ok(backend)
+}
@@ -1,25 +1,108 @@
-use super::general::ScannerResult;
-use crate::scanner::general::{Scanner, ScannerCommons};
-use std::{collections::HashSet, path::PathBuf};
+use super::{elfarch::ElfArch, elfrd::ElfInfo, general::ScannerResult, ldresolv::LdResolver};
+use crate::{rootfs::RootFS, scanner::general::Scanner};
+use std::{
+    collections::{HashMap, HashSet, VecDeque},
+    fs,
+    path::{Path, PathBuf},
+};
 
-pub struct ElfScanner {
-    commons: ScannerCommons,
+pub struct ElfScanner<'a> {
+    resolver: &'a LdResolver,
+    objects: Vec<PathBuf>,
+    needed_by: HashMap<PathBuf, Vec<PathBuf>>,
 }
 
-impl ElfScanner {
-    pub fn new() -> Self {
-        ElfScanner { commons: ScannerCommons::new() }
+impl<'a> ElfScanner<'a> {
+    pub fn new(resolver: &'a LdResolver) -> Self {
+        ElfScanner { resolver, objects: Vec::default(), needed_by: HashMap::default() }
     }
 
+    /// Keep the program interpreter (PT_INTERP) exactly as the object requests it
+    fn collect_interp(&self, obj: &ElfInfo, interp: &str, libs: &mut HashSet<String>) {
+        let ld = PathBuf::from(interp);
+        if !ld.exists() {
+            match self.resolver.find_loader(obj) {
+                Some(found) => log::warn!("Interpreter {} of {:?} is missing, but found {:?}", interp, obj.get_path(), found),
+                None => log::warn!("Interpreter {} of {:?} is missing", interp, obj.get_path()),
+            }
+            return;
+        }
+
+        libs.extend(RootFS::get_link_chain(&ld).iter().map(|p| p.to_str().unwrap_or_default().to_string()));
+    }
+
+    /// Collect dynamic libraries the same order the dynamic loader does: breadth-first,
+    /// and a soname, once loaded, is never searched again.
     fn collect_dl(&mut self, target: String, libs: &mut HashSet<String>) {
-        if let Ok(dpaths) = self.commons.call_libfind(target) {
-            for dep in dpaths {
-                if !libs.contains(&dep) {
-                    self.collect_dl(dep.to_owned(), libs);
+        // Scanner is reused, but objects are always of the last scanned process
+        self.objects.clear();
+        self.needed_by.clear();
+
+        // Main executable has its $ORIGIN at its real location
+        let target = fs::canonicalize(&target).unwrap_or(PathBuf::from(&target));
+        let obj = match ElfInfo::read(&target) {
+            Ok(obj) => obj,
+            Err(err) => {
+                log::debug!("Skipping {:?}: {}", target, err);
+                return;
+            }
+        };
+
+        match ElfArch::find(obj.get_machine(), obj.is_64(), obj.is_big_endian()) {
+            Some(arch) => log::debug!("{:?} is built for {}", target, arch.get_platform()),
+            None => log::warn!("{:?} has unknown architecture (machine type {})", target, obj.get_machine()),
+        }
+
+        self.objects.push(target.to_owned());
+
+        let mut loaded: HashMap<String, PathBuf> = HashMap::default();
+        let mut queue: VecDeque<(ElfInfo, Vec<PathBuf>)> = VecDeque::from([(obj, Vec::default())]);
+
+        while let Some((obj, inherited_rpath)) = queue.pop_front() {
+            if let Some(interp) = obj.get_interp() {
+                self.collect_interp(&obj, interp, libs);
+            }
+
+            // RPATH of the loader is also searched for the dependencies of this object
+            let mut rpath = inherited_rpath.clone();
+            if obj.get_runpath().is_empty() {
+                rpath.splice(0..0, self.resolver.search_dirs(&obj, obj.get_rpath()));
+            }
+
+            for soname in obj.get_needed() {
+                if let Some(dep) = loaded.get(soname) {
+                    self.needed_by.entry(dep.to_owned()).or_default().push(obj.get_path().to_path_buf());
+                    continue;
+                }
+
+                match self.resolver.resolve(&obj, soname, &inherited_rpath) {
+                    Some(dep) => {
+                        loaded.insert(soname.to_owned(), dep.to_owned());
+                        self.objects.push(dep.to_owned());
+                        self.needed_by.entry(dep.to_owned()).or_default().push(obj.get_path().to_path_buf());
+                        match ElfInfo::read(&dep) {
+                            Ok(dobj) => queue.push_back((dobj, rpath.clone())),
+                            Err(err) => log::error!("Unable to read {:?}: {}", dep, err),
+                        }
+                    }
+                    None => {
+                        log::warn!("Unable to resolve {} needed by {:?}", soname, obj.get_path());
+                    }
                 }
-                libs.insert(dep);
             }
         }
+
+        libs.extend(loaded.into_values().map(|p| p.to_str().unwrap_or_default().to_string()));
+    }
+
+    /// Get all ELF objects of the scanned process: main executable first, then libraries in the load order
+    pub fn get_objects(&self) -> &Vec<PathBuf> {
+        &self.objects
+    }
+
+    /// Get objects, those have DT_NEEDED entry to the library
+    pub fn get_needed_by(&self, lib: &Path) -> Vec<PathBuf> {
+        self.needed_by.get(lib).cloned().unwrap_or_default()
     }
 
     /// Find out dynamic libraries in the binary
@@ -36,7 +119,7 @@ impl ElfScanner {
     }
 }
 
-impl Scanner for ElfScanner {
+impl Scanner for ElfScanner<'_> {
     /// Scan for the required dynamic libraries in an executable
     fn scan(&mut self, pth: PathBuf) -> ScannerResult {
         log::debug!("Scanning for dependencies in {}", pth.to_str().unwrap());
@@ -0,0 +1,26 @@
+use super::{pkgdb::PackageDb, traceitf::PkgFileTrace};
+use crate::rootfs::RootFS;
+use std::{collections::HashMap, path::PathBuf};
+
+/// Trace what package a give file belongs to, using the package database.
+pub struct DbPkgFileTrace {
+    file_to_pkg: HashMap<PathBuf, String>,
+}
+
+impl DbPkgFileTrace {
+    /// Constructor from an already loaded package database
+    pub fn from_db<D: PackageDb>(db: &D) -> Self {
+        DbPkgFileTrace { file_to_pkg: db.get_file_owners() }
+    }
+}
+
+impl PkgFileTrace for DbPkgFileTrace {
+    fn trace(&mut self, filename: PathBuf) -> Option<String> {
+        // The database can still have either of the locations, if /bin is a symlink to /usr/bin
+        let mut paths = vec![filename.to_owned()];
+        paths.extend(RootFS::expand_target(filename.to_owned(), false));
+        paths.extend(RootFS::expand_target(filename, true));
+
+        paths.iter().find_map(|p| self.file_to_pkg.get(p).cloned())
+    }
+}
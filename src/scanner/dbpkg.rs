@@ -0,0 +1,182 @@
+use crate::{
+    procdata::Autodeps,
+    profile::DepsPolicy,
+    scanner::{
+        backend::PackageBackend,
+        dbftrace::DbPkgFileTrace,
+        general::{Scanner, ScannerResult},
+        pkgdb::{DbPackage, PackageDb},
+        tracedb::DbPackageTrace,
+        traceitf::{PkgDepTrace, PkgFileTrace},
+    },
+};
+use colored::Colorize;
+use std::{
+    collections::{HashMap, HashSet},
+    io::{Error, ErrorKind},
+    path::PathBuf,
+};
+
+/// Scans content of the package, to which a target belongs to,
+/// using a natively read package database.
+pub struct DbPackageScanner<D: PackageDb> {
+    db: Option<D>,
+    ftrace: Option<DbPkgFileTrace>,
+    autodeps: Autodeps,
+    excluded_packages: HashSet<String>,
+    policy: DepsPolicy,
+    reasons: HashMap<String, String>,
+}
+
+impl<D: PackageDb> DbPackageScanner<D> {
+    /// Constructor
+    pub fn new(autodeps: Autodeps) -> Self {
+        let db = match D::read() {
+            Ok(db) => Some(db),
+            Err(err) => {
+                log::error!("Unable to read {} database: {}", D::NAME, err);
+                None
+            }
+        };
+
+        DbPackageScanner {
+            ftrace: db.as_ref().map(DbPkgFileTrace::from_db),
+            db,
+            autodeps,
+            excluded_packages: HashSet::default(),
+            policy: DepsPolicy::default(),
+            reasons: HashMap::default(),
+        }
+    }
+
+    /// Get the loaded package database
+    fn get_db(&self) -> Result<&D, Error> {
+        self.db.as_ref().ok_or(Error::new(ErrorKind::NotFound, format!("{} database is not available", D::NAME)))
+    }
+
+    /// Get package name of the target binary
+    /// May still not find a package for the target.
+    pub fn get_package_for(&mut self, target: String) -> Result<Option<String>, Error> {
+        match self.ftrace.as_mut() {
+            Some(ftrace) => Ok(ftrace.trace_links(PathBuf::from(target))),
+            None => Err(Error::new(ErrorKind::NotFound, format!("{} database is not available", D::NAME))),
+        }
+    }
+
+    /// Get contents of the package.
+    ///
+    /// If package does not exists, an empty list is returned.
+    pub fn get_package_contents(&self, pkname: String) -> Result<Vec<PathBuf>, Error> {
+        let pkg = match self.get_db()?.get_package(&pkname) {
+            Some(pkg) => pkg,
+            None => return Ok(vec![]),
+        };
+
+        // Preserve [sym]links. Files might be listed, but not installed (e.g. RPM ghost files and
+        // excluded docs) or already removed from the image.
+        Ok(pkg.get_files().iter().filter(|p| p.exists() && !p.is_dir()).cloned().collect())
+    }
+}
+
+impl<D: PackageDb> PackageBackend for DbPackageScanner<D> {
+    fn get_name(&self) -> &'static str {
+        D::NAME
+    }
+
+    fn scan(&mut self, target: PathBuf) -> Vec<PathBuf> {
+        Scanner::scan(self, target).get_paths().to_owned()
+    }
+
+    fn get_owner(&mut self, path: PathBuf) -> Option<String> {
+        self.get_package_for(path.to_str().unwrap_or_default().to_string()).ok().flatten()
+    }
+
+    fn get_contents(&self, pkgname: String) -> Result<Vec<PathBuf>, Error> {
+        self.get_package_contents(pkgname)
+    }
+
+    fn has_package(&self, pkgname: &str) -> bool {
+        matches!(self.get_db(), Ok(db) if db.get_package(pkgname).is_some())
+    }
+
+    fn get_dependencies(&self, pkgname: String) -> (Vec<String>, HashMap<String, String>) {
+        match self.get_db() {
+            Ok(db) => {
+                let mut trace = DbPackageTrace::new(db);
+                let deps = trace
+                    .exclude(self.excluded_packages.clone().into_iter().collect::<Vec<String>>())
+                    .set_policy(self.policy.to_owned())
+                    .trace(pkgname);
+                (deps, trace.get_reasons().to_owned())
+            }
+            Err(_) => (vec![], HashMap::default()),
+        }
+    }
+
+    fn set_deps_policy(&mut self, policy: DepsPolicy) {
+        Scanner::exclude(self, policy.get_exclude().to_owned());
+        self.policy = policy;
+    }
+
+    fn get_reasons(&self) -> &HashMap<String, String> {
+        &self.reasons
+    }
+
+    fn get_db_paths(&self) -> Vec<PathBuf> {
+        D::get_db_paths()
+    }
+}
+
+impl<D: PackageDb> Scanner for DbPackageScanner<D> {
+    fn exclude(&mut self, pkgs: Vec<String>) -> &mut Self {
+        self.excluded_packages.extend(pkgs);
+        self
+    }
+
+    fn scan(&mut self, pth: PathBuf) -> ScannerResult {
+        log::debug!("Scanning {} package contents for {:?}", D::NAME, pth.to_str());
+
+        let mut out: Vec<PathBuf> = vec![];
+        let pkgname = self.get_package_for(pth.to_str().unwrap().to_string());
+
+        if let Ok(Some(pkgname)) = pkgname {
+            log::debug!("{} corresponds to {}", pth.to_str().unwrap(), pkgname);
+
+            match self.get_package_contents(pkgname.to_owned()) {
+                Ok(fp) => {
+                    out.extend(fp);
+                }
+                Err(err) => {
+                    log::error!("Failed getting contents of {}: {}", pkgname, err);
+                }
+            }
+
+            if self.autodeps == Autodeps::Clean || self.autodeps == Autodeps::Free {
+                // Trace dependencies graph for the package
+                let (deps, reasons) = self.get_dependencies(pkgname.to_owned());
+                for p in deps {
+                    log::info!("Keeping dependency package: {} ({})", p.bright_yellow(), reasons[&p]);
+                    self.reasons.entry(p.to_owned()).or_insert(reasons[&p].to_owned());
+                    match self.get_package_contents(p.to_owned()) {
+                        Ok(fp) => {
+                            out.extend(fp);
+                        }
+                        Err(err) => {
+                            log::error!("Failed getting contents of {}: {}", p, err);
+                        }
+                    }
+                }
+            }
+        }
+
+        // Return as an encapsulated object for future reuses
+        ScannerResult::new(out)
+    }
+
+    fn contents(&mut self, pkgname: String) -> Result<ScannerResult, Error> {
+        match self.get_package_contents(pkgname) {
+            Ok(fp) => Ok(ScannerResult::new(fp)),
+            Err(e) => Err(std::io::Error::new(std::io::ErrorKind::InvalidData, e)),
+        }
+    }
+}
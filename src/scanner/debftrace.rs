@@ -3,23 +3,49 @@ use crate::rootfs::RootFS;
 use std::{
     collections::HashMap,
     fs::{self, DirEntry},
-    path::PathBuf,
+    path::{Path, PathBuf},
 };
 
+/// Lists of the package files
+pub const DPKG_INFO: &str = "/var/lib/dpkg/info";
+
+/// Diversions by dpkg-divert: "from", "to" and the diverting package, as three lines
+const DPKG_DIVERSIONS: &str = "/var/lib/dpkg/diversions";
+
+/// Index of the dpkg database: file ownership and package contents.
+/// It is built once, so no dpkg calls are needed.
 pub struct DebPkgFileTrace {
     file_to_pkg: HashMap<PathBuf, String>,
+    pkg_to_files: HashMap<String, Vec<PathBuf>>,
+    diversions: HashMap<PathBuf, (PathBuf, String)>,
+    resolved_dirs: HashMap<PathBuf, Option<PathBuf>>,
 }
 
 impl DebPkgFileTrace {
     pub fn new() -> Self {
-        let mut d = DebPkgFileTrace { file_to_pkg: HashMap::default() };
+        let mut d = DebPkgFileTrace {
+            file_to_pkg: HashMap::default(),
+            pkg_to_files: HashMap::default(),
+            diversions: HashMap::default(),
+            resolved_dirs: HashMap::default(),
+        };
+        d.load_diversions();
         d.load();
         d
     }
 
+    /// Read diversions. Package is ":" for the local diversions by the admin.
+    fn load_diversions(&mut self) {
+        let data = fs::read_to_string(DPKG_DIVERSIONS).unwrap_or_default();
+        let lines = data.lines().collect::<Vec<&str>>();
+        for d in lines.chunks_exact(3) {
+            self.diversions.insert(PathBuf::from(d[0]), (PathBuf::from(d[1]), d[2].to_string()));
+        }
+    }
+
     /// Read dpkg cache. All of it.
     fn load(&mut self) {
-        if let Ok(rd) = fs::read_dir("/var/lib/dpkg/info") {
+        if let Ok(rd) = fs::read_dir(DPKG_INFO) {
             for d in rd.filter_map(Result::ok).collect::<Vec<DirEntry>>() {
                 if d.path().to_str().unwrap().ends_with(".list") {
                     self.load_pkg(d.path());
@@ -41,13 +67,45 @@ impl DebPkgFileTrace {
             .collect::<Vec<&str>>()[0];
 
         if let Ok(pkg_data) = fs::read_to_string(&pinfo) {
-            for f_pth in pkg_data.split('\n').collect::<Vec<&str>>().iter().map(PathBuf::from) {
-                if f_pth.exists() && f_pth.is_file() {
-                    self.file_to_pkg.insert(f_pth, pkgname.to_string().to_owned());
+            let files = pkg_data
+                .lines()
+                .filter(|l| !l.is_empty() && *l != "/.")
+                .map(|l| self.get_diverted(Path::new(l), pkgname))
+                .collect::<Vec<PathBuf>>();
+            for f_pth in files.iter().filter(|p| p.exists() && p.is_file()) {
+                self.file_to_pkg.insert(f_pth.to_owned(), pkgname.to_string());
+
+                // Also as it is seen through the merged /usr, e.g. /bin/bash is /usr/bin/bash
+                if let Some(r_pth) = self.get_resolved(f_pth).filter(|r| r != f_pth) {
+                    self.file_to_pkg.entry(r_pth).or_insert_with(|| pkgname.to_string());
                 }
             }
+            self.pkg_to_files.entry(pkgname.to_string()).or_default().extend(files);
         }
     }
+
+    /// Get the real location of a packaged file, if it was diverted away from the package
+    pub fn get_diverted(&self, p: &Path, pkgname: &str) -> PathBuf {
+        match self.diversions.get(p) {
+            Some((to, by)) if by != pkgname => to.to_owned(),
+            _ => p.to_path_buf(),
+        }
+    }
+
+    /// Get path of the file with its parent directory resolved
+    fn get_resolved(&mut self, p: &Path) -> Option<PathBuf> {
+        let dir = p.parent()?;
+        if !self.resolved_dirs.contains_key(dir) {
+            self.resolved_dirs.insert(dir.to_path_buf(), dir.canonicalize().ok());
+        }
+
+        Some(self.resolved_dirs.get(dir)?.as_ref()?.join(p.file_name()?))
+    }
+
+    /// Get files and directories of the package, as they are placed on the disk
+    pub fn get_contents(&self, pkgname: &str) -> &[PathBuf] {
+        self.pkg_to_files.get(pkgname.split(':').next().unwrap_or_default()).map(|f| f.as_slice()).unwrap_or_default()
+    }
 }
 
 impl PkgFileTrace for DebPkgFileTrace {
@@ -1,14 +1,19 @@
 use crate::{
     procdata::Autodeps,
+    profile::DepsPolicy,
     scanner::{
-        general::{Scanner, ScannerCommons, ScannerResult},
+        backend::PackageBackend,
+        debftrace::DebPkgFileTrace,
+        dpkgstatus::DpkgStatus,
+        dpkgupdate::DpkgUpdate,
+        general::{Scanner, ScannerResult},
         tracedeb,
-        traceitf::PkgDepTrace,
+        traceitf::{PkgDepTrace, PkgFileTrace},
     },
 };
 use colored::Colorize;
 use std::{
-    collections::HashSet,
+    collections::{HashMap, HashSet},
     io::{Error, ErrorKind},
     path::PathBuf,
 };
@@ -16,79 +21,115 @@ use std::{
 /// Scans content of the package, to which
 /// a target belongs to.
 pub struct DebPackageScanner {
-    commons: ScannerCommons,
+    status: Option<DpkgStatus>,
+    index: DebPkgFileTrace,
     autodeps: Autodeps,
     excluded_packages: HashSet<String>,
+    policy: DepsPolicy,
+    reasons: HashMap<String, String>,
 }
 
 impl DebPackageScanner {
     /// Constructor
     pub fn new(autodeps: Autodeps) -> Self {
-        DebPackageScanner { commons: ScannerCommons::new(), autodeps, excluded_packages: HashSet::default() }
-    }
-
-    /// Expands target taking to the account Linux /bin symlinks to /usr/bin etc.
-    ///
-    /// This is needed as dpkg won't always find the corresponding package, because
-    /// the database still pointing to the old-fashioned location (e.g. "/bin").
-    /// In this case fall-back is used to find also in "/bin/<binary>" if
-    /// search for the "/usr/bin/<binary>" fails.
-    fn expand_target(&self, target: String) -> Vec<String> {
-        let mut p = PathBuf::from(&target);
-        let fname = p.file_name().unwrap().to_owned();
-
-        p.pop();
-        let fdir = p.to_str().unwrap();
-
-        if fdir == "/usr/bin" {
-            return vec![
-                format!("{}", PathBuf::from(fdir).join(&fname).to_str().unwrap()),
-                format!("{}", PathBuf::from("/bin").join(fname).to_str().unwrap()),
-            ];
+        let status = match DpkgStatus::read() {
+            Ok(status) => Some(status),
+            Err(err) => {
+                log::error!("Unable to read dpkg status: {}", err);
+                None
+            }
+        };
+
+        // Whole dpkg database is indexed only once
+        DebPackageScanner {
+            status,
+            index: DebPkgFileTrace::new(),
+            autodeps,
+            excluded_packages: HashSet::default(),
+            policy: DepsPolicy::default(),
+            reasons: HashMap::default(),
         }
-
-        vec![target]
     }
 
     /// Get package name of the target binary
     /// May still not find a package for the target.
-    pub fn get_package_for(&self, target: String) -> Result<Option<String>, Error> {
-        let dpkg = PathBuf::from("/usr/bin/dpkg");
-        if !dpkg.exists() {
-            return Err(Error::new(ErrorKind::NotFound, format!("Unable to access \"{}\"", dpkg.to_str().unwrap())));
+    pub fn get_package_for(&mut self, target: String) -> Result<Option<String>, Error> {
+        if self.status.is_none() {
+            return Err(Error::new(ErrorKind::NotFound, "dpkg database is not available"));
         }
 
-        let mut pkg: String = String::default();
-        for t in self.expand_target(target) {
-            if let Some(l) = (self.commons.call_any(dpkg.to_owned(), &["-S".to_string(), t.to_string()])?).into_iter().next() {
-                pkg = l.split_once(':').unwrap().0.trim().to_string();
-                break;
-            }
-        }
-
-        if pkg.is_empty() {
-            Ok(None)
-        } else {
-            Ok(Some(pkg))
-        }
+        Ok(self.index.trace_links(PathBuf::from(target)))
     }
 
     /// Get contents of the package.
     ///
-    /// If package does not exists or dpkg database has no contents, an empty lines returned.
+    /// If package does not exists or dpkg database has no contents, an empty list is returned.
     pub fn get_package_contents(&self, pkname: String) -> Result<Vec<PathBuf>, Error> {
-        let mut files: Vec<PathBuf> = Vec::default();
-        for fp in self.commons.call_any("/usr/bin/dpkg".into(), &["-L".to_string(), pkname])? {
-            if fp == "./" {
-                continue;
+        // Preserve [sym]links. Files, excluded by dpkg path-exclude, are still listed.
+        Ok(self.index.get_contents(&pkname).iter().filter(|p| p.exists() && !p.is_dir()).cloned().collect())
+    }
+}
+
+impl PackageBackend for DebPackageScanner {
+    fn get_name(&self) -> &'static str {
+        "dpkg"
+    }
+
+    fn scan(&mut self, target: PathBuf) -> Vec<PathBuf> {
+        Scanner::scan(self, target).get_paths().to_owned()
+    }
+
+    fn get_owner(&mut self, path: PathBuf) -> Option<String> {
+        self.index.trace_links(path)
+    }
+
+    fn get_contents(&self, pkgname: String) -> Result<Vec<PathBuf>, Error> {
+        self.get_package_contents(pkgname)
+    }
+
+    fn has_package(&self, pkgname: &str) -> bool {
+        matches!(&self.status, Some(status) if status.get_package(pkgname).is_some())
+    }
+
+    fn get_dependencies(&self, pkgname: String) -> (Vec<String>, HashMap<String, String>) {
+        match &self.status {
+            Some(status) => {
+                let mut trace = tracedeb::DebPackageTrace::new(status);
+                let deps = trace
+                    .exclude(self.excluded_packages.clone().into_iter().collect::<Vec<String>>())
+                    .set_policy(self.policy.to_owned())
+                    .trace(pkgname);
+                (deps, trace.get_reasons().to_owned())
             }
+            None => (vec![], HashMap::default()),
+        }
+    }
 
-            // Preserve [sym]links
-            if !PathBuf::from(&fp).is_dir() {
-                files.push(PathBuf::from(fp));
+    fn set_deps_policy(&mut self, policy: DepsPolicy) {
+        Scanner::exclude(self, policy.get_exclude().to_owned());
+        self.policy = policy;
+    }
+
+    fn get_reasons(&self) -> &HashMap<String, String> {
+        &self.reasons
+    }
+
+    fn get_conffiles(&self) -> HashMap<PathBuf, (String, String)> {
+        let mut out: HashMap<PathBuf, (String, String)> = HashMap::default();
+        for pkg in self.status.iter().flat_map(|s| s.get_packages()) {
+            for (p, sum) in pkg.get_conffiles() {
+                out.insert(p, (pkg.get_name().to_string(), sum));
             }
         }
-        Ok(files)
+        out
+    }
+
+    fn get_db_paths(&self) -> Vec<PathBuf> {
+        vec![DpkgStatus::get_db_dir()]
+    }
+
+    fn update_db(&mut self) -> Result<(), Error> {
+        DpkgUpdate::new(&self.index).update()
     }
 }
 
@@ -118,11 +159,10 @@ impl Scanner for DebPackageScanner {
 
             if self.autodeps == Autodeps::Clean || self.autodeps == Autodeps::Free {
                 // Trace dependencies graph for the package
-                for p in tracedeb::DebPackageTrace::new()
-                    .exclude(self.excluded_packages.clone().into_iter().collect::<Vec<String>>())
-                    .trace(pkgname.to_owned())
-                {
-                    log::info!("Keeping dependency package: {}", p.bright_yellow());
+                let (deps, reasons) = self.get_dependencies(pkgname.to_owned());
+                for p in deps {
+                    log::info!("Keeping dependency package: {} ({})", p.bright_yellow(), reasons[&p]);
+                    self.reasons.entry(p.to_owned()).or_insert(reasons[&p].to_owned());
                     match self.get_package_contents(p.to_owned()) {
                         Ok(fp) => {
                             out.extend(fp);
@@ -8,30 +8,19 @@
     symlinks in place.
 */
 
-use super::{debftrace::DebPkgFileTrace, rpmftrace::RpmPkgFileTrace, traceitf::PkgFileTrace};
+use super::backend::PackageBackend;
 use std::path::PathBuf;
 
 pub struct _DeltaFinder {
     dupes: Vec<PathBuf>,
     rootfs: PathBuf,
-    pkgtrace: Box<dyn PkgFileTrace>,
+    backend: Box<dyn PackageBackend>,
 }
 
 impl _DeltaFinder {
-    pub fn _new(rootfs: Option<PathBuf>) -> Self {
-        let debian_family = ["ubuntu", "debian", "mint"];
-        //let redhat_family = vec!["redhat", "fedora", "suse", "sles", "opensuse-leap", "opensuse"];
-
-        let os_id = sys_info::linux_os_release().unwrap().id().to_lowercase();
-        _DeltaFinder {
-            dupes: Vec::new(),
-            rootfs: rootfs.unwrap_or(PathBuf::from("/")),
-            pkgtrace: if debian_family.contains(&os_id.as_str()) {
-                Box::new(DebPkgFileTrace::new())
-            } else {
-                Box::new(RpmPkgFileTrace::_new())
-            },
-        }
+    /// Backend should be of the image (see backend::get_backend), not of the current system
+    pub fn _new(rootfs: Option<PathBuf>, backend: Box<dyn PackageBackend>) -> Self {
+        _DeltaFinder { dupes: Vec::new(), rootfs: rootfs.unwrap_or(PathBuf::from("/")), backend }
     }
 
     /// Test if a given path has a carbon copy on the current system
@@ -0,0 +1,127 @@
+/*
+dlopen() heuristics.
+
+Libraries and plugins, loaded with dlopen(), are never in DT_NEEDED.
+Their names are still often hardcoded as strings in the read-only data,
+so such strings are matched against the libraries of the image.
+ */
+
+use super::{
+    binlib::ElfScanner,
+    elfrd::{ElfInfo, ElfStrings},
+    general::Scanner,
+    ldresolv::LdResolver,
+};
+use std::{
+    collections::{HashMap, HashSet, VecDeque},
+    path::{Path, PathBuf},
+};
+
+/// Shortest possible name, like "libz.so"
+const MIN_NAME_LEN: usize = 7;
+
+pub struct DlopenScanner<'a> {
+    resolver: &'a LdResolver,
+    elfscan: ElfScanner<'a>,
+    found: HashMap<PathBuf, String>,
+}
+
+impl<'a> DlopenScanner<'a> {
+    pub fn new(resolver: &'a LdResolver) -> Self {
+        DlopenScanner { resolver, elfscan: ElfScanner::new(resolver), found: HashMap::default() }
+    }
+
+    /// Returns true if the file name looks like a shared object: "foo.so" or "foo.so.1.2"
+    fn is_so_name(name: &str) -> bool {
+        match name.split_once(".so") {
+            Some((stem, ver)) => {
+                !stem.is_empty()
+                    && stem.chars().all(|c| c.is_ascii_alphanumeric() || "_-+.".contains(c))
+                    && (ver.is_empty() || (ver.starts_with('.') && ver[1..].chars().all(|c| c.is_ascii_digit() || c == '.')))
+            }
+            None => false,
+        }
+    }
+
+    /// Returns true if the string looks like a soname, e.g. "libfoo.so.3"
+    fn is_soname(s: &str) -> bool {
+        s.starts_with("lib") && Self::is_so_name(s)
+    }
+
+    /// Returns true if the string is an absolute path to a shared object
+    fn is_so_path(s: &str) -> bool {
+        s.starts_with('/')
+            && !s.contains(char::is_whitespace)
+            && Self::is_so_name(Path::new(s).file_name().unwrap_or_default().to_str().unwrap_or_default())
+    }
+
+    /// Find shared objects, those are mentioned in the object
+    fn get_candidates(&self, obj: &ElfInfo) -> Vec<PathBuf> {
+        let mut out: Vec<PathBuf> = Vec::default();
+        let strings = match ElfStrings::read(obj.get_path(), MIN_NAME_LEN) {
+            Ok(strings) => strings,
+            Err(_) => return out,
+        };
+
+        for s in strings.get_strings() {
+            let p = if Self::is_soname(s) {
+                if obj.get_needed().contains(s) {
+                    continue;
+                }
+                self.resolver.resolve(obj, s, &[])
+            } else if Self::is_so_path(s) {
+                Some(PathBuf::from(s)).filter(|p| {
+                    p.is_file() && ElfInfo::read_header(p) == Some((obj.get_machine(), obj.is_64(), obj.is_big_endian()))
+                })
+            } else {
+                None
+            };
+
+            if let Some(p) = p {
+                out.push(p);
+            }
+        }
+
+        out
+    }
+
+    /// Scan kept objects for the dynamically loaded libraries.
+    /// Found libraries are also scanned, as they might load other ones.
+    ///
+    /// Returns found libraries with their dependencies and the reasons they were added.
+    pub fn scan(&mut self, objects: &HashSet<PathBuf>) -> &HashMap<PathBuf, String> {
+        let mut queue: VecDeque<PathBuf> = objects.iter().filter(|p| ElfInfo::read_header(p).is_some()).cloned().collect();
+        let mut visited: HashSet<PathBuf> = HashSet::default();
+
+        while let Some(p) = queue.pop_front() {
+            if !visited.insert(p.to_owned()) {
+                continue;
+            }
+
+            let obj = match ElfInfo::read(&p) {
+                Ok(obj) => obj,
+                Err(_) => continue,
+            };
+
+            for lib in self.get_candidates(&obj) {
+                if objects.contains(&lib) || self.found.contains_key(&lib) {
+                    continue;
+                }
+
+                log::info!("Possibly dlopen() of {:?} by {:?}", lib, p);
+                self.found.insert(lib.to_owned(), format!("heuristic: dlopen() by {}", p.to_str().unwrap_or_default()));
+
+                for dep in self.elfscan.scan(lib.to_owned()).get_paths().iter().filter(|d| !objects.contains(*d)) {
+                    self.found.entry(dep.to_owned()).or_insert(format!(
+                        "heuristic: needed by {}",
+                        lib.file_name().unwrap_or_default().to_str().unwrap_or_default()
+                    ));
+                }
+
+                queue.push_back(lib);
+            }
+        }
+
+        &self.found
+    }
+}
@@ -2,17 +2,13 @@
 Data lister (fancy STDOUT printer)
 */
 
-use super::{debpkg::DebPackageScanner, general::Scanner};
-use crate::{
-    filters::resources,
-    procdata,
-    scanner::{debftrace::DebPkgFileTrace, traceitf::PkgFileTrace},
-};
+use super::backend::PackageBackend;
+use crate::filters::resources;
 use bytesize::ByteSize;
 use colored::Colorize;
 use filesize::PathExt;
 use std::{
-    collections::HashSet,
+    collections::{HashMap, HashSet},
     os::unix::prelude::PermissionsExt,
     path::{Path, PathBuf},
 };
@@ -24,11 +20,13 @@ pub struct ContentFormatter<'a> {
     last_dir: String,
     fs_removed: Option<&'a Vec<PathBuf>>,
     bundled_packages: Option<&'a Vec<String>>,
+    reasons: Option<&'a HashMap<PathBuf, String>>,
+    backend: Option<&'a mut dyn PackageBackend>,
 }
 
 impl<'a> ContentFormatter<'a> {
     pub(crate) fn new(fs_data: &'a Vec<PathBuf>) -> Self {
-        Self { fs_data, last_dir: "".to_string(), fs_removed: None, bundled_packages: None }
+        Self { fs_data, last_dir: "".to_string(), fs_removed: None, bundled_packages: None, reasons: None, backend: None }
     }
 
     /// Set removed data
@@ -46,6 +44,26 @@ impl<'a> ContentFormatter<'a> {
         self
     }
 
+    /// Set reasons why some paths are kept, e.g. found by heuristics
+    pub(crate) fn set_reasons(&mut self, r: &'a HashMap<PathBuf, String>) -> &mut Self {
+        self.reasons = Some(r);
+        self
+    }
+
+    /// Set package backend of the image
+    pub(crate) fn set_backend(&mut self, backend: &'a mut dyn PackageBackend) -> &mut Self {
+        self.backend = Some(backend);
+        self
+    }
+
+    /// Get reason of a path, formatted
+    fn get_reason(&self, p: &Path) -> String {
+        match self.reasons.and_then(|r| r.get(p)) {
+            Some(r) => format!("  {}", format!("[{}]", r).yellow()),
+            None => "".to_string(),
+        }
+    }
+
     /// Perform only a dry-run
     fn format_removed(&self) -> (u64, u64) {
         let mut total_size: u64 = 0;
@@ -68,21 +86,20 @@ impl<'a> ContentFormatter<'a> {
         let mut pkgs: HashSet<String> = HashSet::default();
         let mut s_pkgs: Vec<String> = Vec::default();
 
-        // XXX: Depends on the system
-        let mut pt: Box<dyn PkgFileTrace> = Box::new(DebPkgFileTrace::new());
-
-        self.fs_data.iter().for_each(|p: &PathBuf| {
-            if let Some(pkg) = pt.trace(p.clone()) {
-                pkgs.insert(pkg);
-            }
-        });
+        if let Some(backend) = self.backend.as_mut() {
+            self.fs_data.iter().for_each(|p: &PathBuf| {
+                if let Some(pkg) = backend.get_owner(p.clone()) {
+                    pkgs.insert(pkg);
+                }
+            });
+        }
         let mut pkgs: Vec<String> = pkgs.into_iter().collect::<Vec<String>>();
         pkgs.sort();
 
         // Collect system-linked packages
-        if self.bundled_packages.is_some() {
+        if let Some(bundled_packages) = self.bundled_packages {
             for p in &pkgs {
-                if !self.bundled_packages.unwrap().contains(p) {
+                if !bundled_packages.contains(p) {
                     s_pkgs.push(p.to_string());
                 }
             }
@@ -93,11 +110,10 @@ impl<'a> ContentFormatter<'a> {
     }
 
     fn get_pkg_total_size(&mut self, pknames: Vec<String>) -> i128 {
-        let mut t: i128 = 0;
-        let mut ps = DebPackageScanner::new(procdata::Autodeps::Undef);
-        pknames.iter().for_each(|p| t += ps.contents(p.to_string()).unwrap_or_default().get_size());
-
-        t
+        match &self.backend {
+            Some(backend) => backend.get_size(&pknames),
+            None => 0,
+        }
     }
 
     #[allow(clippy::println_empty_string)]
@@ -136,17 +152,19 @@ impl<'a> ContentFormatter<'a> {
                 (d_total, d_size) = (0, 0);
             }
 
+            let reason = self.get_reason(p);
             if p.is_symlink() {
                 println!(
-                    "{} {} {} {}{}",
+                    "{} {} {} {}{}{}",
                     leaf.blue(),
                     fname.bright_cyan().bold(),
                     "⮕".yellow().dimmed(),
                     p.read_link().unwrap().as_path().to_str().unwrap().cyan(),
+                    reason,
                     t_leaf
                 );
             } else if p.metadata().unwrap().permissions().mode() & 0o111 != 0 {
-                println!("{} {}{}", leaf.blue(), fname.bright_green().bold(), t_leaf);
+                println!("{} {}{}{}", leaf.blue(), fname.bright_green().bold(), reason, t_leaf);
             } else {
                 if fname.ends_with(".so") || fname.contains(".so.") {
                     fname = fname.green().to_string();
@@ -156,7 +174,7 @@ impl<'a> ContentFormatter<'a> {
                     fname = format!("{}  {}", "⚠️".bright_red().bold(), fname.bright_red());
                 }
 
-                println!("{} {}{}", leaf.blue(), fname, t_leaf);
+                println!("{} {}{}{}", leaf.blue(), fname, reason, t_leaf);
             }
 
             d_total += 1;
@@ -176,6 +194,13 @@ impl<'a> ContentFormatter<'a> {
             (d_len + 1).to_string().bright_green(),
             ByteSize::b(t_size).to_string().bright_yellow()
         );
+        if let Some(r) = self.reasons.filter(|r| !r.is_empty()) {
+            println!(
+                "Preserved {} files by heuristics or with local changes, {}",
+                self.fs_data.iter().filter(|p| r.contains_key(*p)).count().to_string().bright_yellow(),
+                "please review them".yellow()
+            );
+        }
         if j_total > 0 {
             println!(
                 "Potentially {} junk files, taking {} of a disk space",
@@ -190,6 +215,15 @@ impl<'a> ContentFormatter<'a> {
             pkgs.join(", ")
         );
 
+        // Relations, those pulled in the dependency packages
+        if let Some(r) = self.backend.as_deref().map(|b| b.get_reasons()).filter(|r| !r.is_empty()) {
+            println!("{}", "Dependency packages were pulled in as follows:".yellow());
+            for p in pkgs.iter().filter(|p| r.contains_key(*p)) {
+                println!("  {} {}", p, format!("[{}]", r[p]).yellow());
+            }
+            println!();
+        }
+
         if !s_pkgs.is_empty() {
             println!(
                 "{} {} {} {}\n  {}\n",
@@ -0,0 +1,340 @@
+/*
+dpkg status database reader.
+
+The status file is a list of RFC822-like stanzas, one per package.
+Relations (Depends, Pre-Depends etc) are resolved against the installed
+packages and their virtual Provides, with the version constraints
+compared the same way dpkg does it.
+ */
+
+use std::{
+    cmp::Ordering,
+    collections::HashMap,
+    fs,
+    io::Error,
+    path::{Path, PathBuf},
+};
+
+/// dpkg database of the installed packages
+pub const DPKG_STATUS: &str = "/var/lib/dpkg/status";
+
+/// Relation to another package, e.g. "libc6:amd64 (>= 2.34)"
+pub struct DpkgDep {
+    name: String,
+    arch: Option<String>,
+    constraint: Option<(String, String)>,
+}
+
+impl DpkgDep {
+    /// Parse single relation: "name[:arch] [(op version)]"
+    fn parse(s: &str) -> Option<Self> {
+        let s = s.trim();
+        let (name, constraint) = match s.split_once('(') {
+            Some((name, c)) => {
+                let c = c.trim_end_matches(')').trim();
+                let op_len = c.chars().take_while(|c| "<>=".contains(*c)).count();
+                (name.trim(), Some((c[..op_len].to_string(), c[op_len..].trim().to_string())))
+            }
+            None => (s, None),
+        };
+
+        if name.is_empty() {
+            return None;
+        }
+
+        let (name, arch) = match name.split_once(':') {
+            Some((n, a)) => (n, Some(a.to_string())),
+            None => (name, None),
+        };
+
+        Some(DpkgDep { name: name.to_string(), arch, constraint })
+    }
+
+    /// Package name
+    pub fn get_name(&self) -> &str {
+        &self.name
+    }
+
+    /// Returns true if the version satisfies the constraint
+    fn accepts(&self, version: Option<&str>) -> bool {
+        let (op, req) = match &self.constraint {
+            Some(c) => c,
+            None => return true,
+        };
+
+        // Unversioned provides never satisfy versioned relations
+        let version = match version {
+            Some(v) => v,
+            None => return false,
+        };
+
+        let o = DpkgStatus::cmp_versions(version, req);
+        match op.as_str() {
+            "<<" => o == Ordering::Less,
+            "<=" | "<" => o != Ordering::Greater,
+            "=" => o == Ordering::Equal,
+            ">=" | ">" => o != Ordering::Less,
+            ">>" => o == Ordering::Greater,
+            _ => false,
+        }
+    }
+}
+
+/// Installed package, as recorded in the status file
+pub struct DpkgPackage {
+    name: String,
+    arch: String,
+    version: String,
+    fields: HashMap<String, String>,
+}
+
+impl DpkgPackage {
+    /// Package name
+    pub fn get_name(&self) -> &str {
+        &self.name
+    }
+
+    /// Raw value of a field. Continuation lines are joined with a newline.
+    pub fn get_field(&self, name: &str) -> Option<&str> {
+        self.fields.get(name).map(|f| f.as_str())
+    }
+
+    /// Get relations of a field (e.g. "Depends") as groups of alternatives
+    pub fn get_relations(&self, field: &str) -> Vec<Vec<DpkgDep>> {
+        self.get_field(field)
+            .unwrap_or_default()
+            .split(',')
+            .map(|g| g.split('|').filter_map(DpkgDep::parse).collect::<Vec<DpkgDep>>())
+            .filter(|g| !g.is_empty())
+            .collect()
+    }
+
+    /// Configuration files with the checksums of their packaged versions.
+    /// Obsolete ones, those are no longer shipped by the package, are skipped.
+    pub fn get_conffiles(&self) -> Vec<(PathBuf, String)> {
+        self.get_field("Conffiles")
+            .unwrap_or_default()
+            .lines()
+            .filter_map(|l| {
+                let mut f = l.split_whitespace();
+                let (p, sum) = (f.next()?, f.next()?);
+                if f.any(|flag| flag == "obsolete") {
+                    return None;
+                }
+                Some((PathBuf::from(p), sum.to_string()))
+            })
+            .collect()
+    }
+
+    /// Returns true if the package can satisfy a relation of another package of the given architecture
+    fn is_arch_ok(&self, dep: &DpkgDep, arch: &str) -> bool {
+        match dep.arch.as_deref() {
+            Some("any") => true,
+            Some("native") | None => self.arch == arch || self.arch == "all" || arch == "all",
+            Some(a) => self.arch == a,
+        }
+    }
+}
+
+pub struct DpkgStatus {
+    packages: Vec<DpkgPackage>,
+    by_name: HashMap<String, Vec<usize>>,
+    provided: HashMap<String, Vec<(usize, Option<String>)>>,
+}
+
+impl DpkgStatus {
+    /// Returns true if dpkg is the package manager of the image
+    pub fn exists() -> bool {
+        Path::new(DPKG_STATUS).exists()
+    }
+
+    /// Get directory of the whole dpkg database
+    pub fn get_db_dir() -> PathBuf {
+        Path::new(DPKG_STATUS).parent().unwrap_or(Path::new("/")).to_path_buf()
+    }
+
+    /// Read all installed packages
+    pub fn read() -> Result<Self, Error> {
+        let mut st = DpkgStatus { packages: Vec::default(), by_name: HashMap::default(), provided: HashMap::default() };
+        for stanza in fs::read_to_string(DPKG_STATUS)?.split("\n\n") {
+            if let Some(pkg) = Self::parse_stanza(stanza) {
+                st.add(pkg);
+            }
+        }
+
+        log::debug!("Found {} installed dpkg packages", st.packages.len());
+        Ok(st)
+    }
+
+    /// Parse package stanza. Packages, those are not installed, are skipped.
+    fn parse_stanza(stanza: &str) -> Option<DpkgPackage> {
+        let mut fields: HashMap<String, String> = HashMap::default();
+        let mut last = String::default();
+        for l in stanza.lines() {
+            if l.starts_with([' ', '\t']) {
+                if let Some(v) = fields.get_mut(&last) {
+                    v.push('\n');
+                    v.push_str(l.trim());
+                }
+            } else if let Some((k, v)) = l.split_once(':') {
+                last = k.to_string();
+                fields.insert(last.to_owned(), v.trim().to_string());
+            }
+        }
+
+        // "want flag status", e.g. "install ok installed". Removed packages can still have conffiles.
+        let status = fields.get("Status")?.split_whitespace().last().unwrap_or_default();
+        if status == "not-installed" || status == "config-files" {
+            return None;
+        }
+
+        Some(DpkgPackage {
+            name: fields.get("Package")?.to_owned(),
+            arch: fields.get("Architecture").cloned().unwrap_or_default(),
+            version: fields.get("Version").cloned().unwrap_or_default(),
+            fields,
+        })
+    }
+
+    /// Register package and its virtual packages
+    fn add(&mut self, pkg: DpkgPackage) {
+        let idx = self.packages.len();
+        self.by_name.entry(pkg.name.to_owned()).or_default().push(idx);
+        for p in pkg.get_relations("Provides").into_iter().flatten() {
+            let version = p.constraint.as_ref().filter(|(op, _)| op == "=").map(|(_, v)| v.to_owned());
+            self.provided.entry(p.name).or_default().push((idx, version));
+        }
+        self.packages.push(pkg);
+    }
+
+    /// Get all installed packages
+    pub fn get_packages(&self) -> &[DpkgPackage] {
+        &self.packages
+    }
+
+    /// Get installed package by its name. For multiarch packages the first one is taken.
+    pub fn get_package(&self, name: &str) -> Option<&DpkgPackage> {
+        let name = name.split_once(':').map(|(n, _)| n).unwrap_or(name);
+        self.by_name.get(name).and_then(|p| p.first()).map(|i| &self.packages[*i])
+    }
+
+    /// Find an installed package, which satisfies the relation group of a package of the given architecture.
+    /// Alternatives are tried in their order: real packages first, then the virtual ones.
+    pub fn resolve(&self, group: &[DpkgDep], arch: &str) -> Option<&DpkgPackage> {
+        for dep in group {
+            let real = self.by_name.get(&dep.name).into_iter().flatten().map(|i| &self.packages[*i]);
+            if let Some(p) = real.filter(|p| p.is_arch_ok(dep, arch)).find(|p| dep.accepts(Some(&p.version))) {
+                return Some(p);
+            }
+
+            if let Some(p) = self
+                .provided
+                .get(&dep.name)
+                .into_iter()
+                .flatten()
+                .filter(|(i, _)| self.packages[*i].is_arch_ok(dep, arch))
+                .find(|(_, v)| dep.accepts(v.as_deref()))
+                .map(|(i, _)| &self.packages[*i])
+            {
+                return Some(p);
+            }
+        }
+
+        None
+    }
+
+    /// Split version to epoch, upstream version and revision
+    fn split_version(v: &str) -> (u64, &str, &str) {
+        let (epoch, rest) = match v.split_once(':') {
+            Some((e, r)) => (e.parse::<u64>().unwrap_or_default(), r),
+            None => (0, v),
+        };
+
+        match rest.rsplit_once('-') {
+            Some((u, r)) => (epoch, u, r),
+            None => (epoch, rest, ""),
+        }
+    }
+
+    /// Sort weight of a non-digit character: "~" is before anything, even the end of the string
+    fn char_order(c: Option<u8>) -> i32 {
+        match c {
+            None => 0,
+            Some(b'~') => -1,
+            Some(c) if c.is_ascii_digit() => 0,
+            Some(c) if c.is_ascii_alphabetic() => c as i32,
+            Some(c) => c as i32 + 256,
+        }
+    }
+
+    /// Compare upstream versions or revisions
+    fn cmp_part(a: &str, b: &str) -> Ordering {
+        let (mut a, mut b) = (a.as_bytes(), b.as_bytes());
+        while !a.is_empty() || !b.is_empty() {
+            // Non-digit prefix
+            while matches!(a.first(), Some(c) if !c.is_ascii_digit()) || matches!(b.first(), Some(c) if !c.is_ascii_digit()) {
+                let (ac, bc) = (Self::char_order(a.first().copied()), Self::char_order(b.first().copied()));
+                if ac != bc {
+                    return ac.cmp(&bc);
+                }
+                a = a.get(1..).unwrap_or_default();
+                b = b.get(1..).unwrap_or_default();
+            }
+
+            // Numeric part
+            let an = a.iter().take_while(|c| c.is_ascii_digit()).count();
+            let bn = b.iter().take_while(|c| c.is_ascii_digit()).count();
+            let (ad, bd) = (&a[..an], &b[..bn]);
+            let ad = &ad[ad.iter().take_while(|c| **c == b'0').count()..];
+            let bd = &bd[bd.iter().take_while(|c| **c == b'0').count()..];
+            match ad.len().cmp(&bd.len()).then(ad.cmp(bd)) {
+                Ordering::Equal => {}
+                o => return o,
+            }
+            a = &a[an..];
+            b = &b[bn..];
+        }
+
+        Ordering::Equal
+    }
+
+    /// Compare Debian versions, like "1:2.36-9+deb12u4"
+    pub fn cmp_versions(a: &str, b: &str) -> Ordering {
+        let (ae, au, ar) = Self::split_version(a);
+        let (be, bu, br) = Self::split_version(b);
+
+        ae.cmp(&be).then_with(|| Self::cmp_part(au, bu)).then_with(|| Self::cmp_part(ar, br))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::DpkgStatus;
+    use std::cmp::Ordering;
+
+    #[test]
+    fn test_cmp_versions_tilde() {
+        // Tilde sorts before anything, even the end of the version
+        assert_eq!(DpkgStatus::cmp_versions("1.0~rc1", "1.0"), Ordering::Less);
+        assert_eq!(DpkgStatus::cmp_versions("1.0~~", "1.0~"), Ordering::Less);
+        assert_eq!(DpkgStatus::cmp_versions("1.0~rc1", "1.0~rc2"), Ordering::Less);
+        assert_eq!(DpkgStatus::cmp_versions("2.36-9~bpo1", "2.36-9"), Ordering::Less);
+    }
+
+    #[test]
+    fn test_cmp_versions_epoch() {
+        // Epoch wins over the rest of the version, missing epoch is zero
+        assert_eq!(DpkgStatus::cmp_versions("1:1.0", "2.0"), Ordering::Greater);
+        assert_eq!(DpkgStatus::cmp_versions("0:1.0", "1.0"), Ordering::Equal);
+        assert_eq!(DpkgStatus::cmp_versions("1:2.36-9+deb12u4", "2:1.0"), Ordering::Less);
+    }
+
+    #[test]
+    fn test_cmp_versions_parts() {
+        assert_eq!(DpkgStatus::cmp_versions("1.10", "1.9"), Ordering::Greater);
+        assert_eq!(DpkgStatus::cmp_versions("1.01", "1.1"), Ordering::Equal);
+        assert_eq!(DpkgStatus::cmp_versions("1.0a", "1.0+"), Ordering::Less);
+        assert_eq!(DpkgStatus::cmp_versions("2.36-9+deb12u4", "2.36-9+deb12u10"), Ordering::Less);
+        assert_eq!(DpkgStatus::cmp_versions("1.0-1", "1.0"), Ordering::Greater);
+    }
+}
@@ -0,0 +1,149 @@
+/*
+dpkg database update.
+
+After the image is tinted, the database still describes the full package set.
+Packages without any files left on the disk are removed from the status file
+together with their control files. File lists (.list and .md5sums) of the
+partially pruned packages are rewritten to what is still on the disk.
+ */
+
+use super::{
+    debftrace::{DebPkgFileTrace, DPKG_INFO},
+    dpkgstatus::DPKG_STATUS,
+};
+use std::{
+    collections::HashSet,
+    fs,
+    io::Error,
+    path::{Path, PathBuf},
+};
+
+/// Control files of a package in the dpkg database
+const CONTROL_FILES: &[&str] = &[
+    "list",
+    "md5sums",
+    "conffiles",
+    "postinst",
+    "prerm",
+    "postrm",
+    "preinst",
+    "triggers",
+    "shlibs",
+    "symbols",
+    "templates",
+    "config",
+];
+
+pub struct DpkgUpdate<'a> {
+    index: &'a DebPkgFileTrace,
+}
+
+impl<'a> DpkgUpdate<'a> {
+    pub fn new(index: &'a DebPkgFileTrace) -> Self {
+        DpkgUpdate { index }
+    }
+
+    /// Returns true if the packaged path is still on the disk, at its diverted location if any
+    fn is_present(&self, p: &Path, pkgname: &str) -> bool {
+        self.index.get_diverted(p, pkgname).symlink_metadata().is_ok()
+    }
+
+    /// Rewrite file list of the package to the paths, those are still on the disk.
+    /// Returns false, if no files of the package are left and it should be removed.
+    fn update_list(&self, list: &Path, pkgname: &str) -> Result<bool, Error> {
+        let data = fs::read_to_string(list)?;
+        let lines = data.lines().filter(|l| !l.is_empty()).collect::<Vec<&str>>();
+        let kept = lines.iter().filter(|l| self.is_present(Path::new(l), pkgname)).cloned().collect::<Vec<&str>>();
+
+        if kept.len() == lines.len() {
+            return Ok(true);
+        }
+
+        // Only directories are left, those are shared with other packages
+        if !kept.iter().any(|l| !self.index.get_diverted(Path::new(l), pkgname).is_dir()) {
+            return Ok(false);
+        }
+
+        fs::write(list, format!("{}\n", kept.join("\n")))?;
+        Ok(true)
+    }
+
+    /// Rewrite checksums of the package. Paths there are relative to the root.
+    fn update_md5sums(&self, md5sums: &Path, pkgname: &str) -> Result<(), Error> {
+        let data = match fs::read_to_string(md5sums) {
+            Ok(data) => data,
+            Err(_) => return Ok(()),
+        };
+
+        let kept = data
+            .lines()
+            .filter(|l| match l.split_once(char::is_whitespace) {
+                Some((_, p)) => self.is_present(&Path::new("/").join(p.trim_start()), pkgname),
+                None => false,
+            })
+            .collect::<Vec<&str>>();
+
+        fs::write(md5sums, if kept.is_empty() { "".to_string() } else { format!("{}\n", kept.join("\n")) })
+    }
+
+    /// Remove all control files of the package, e.g. "bash.list", "bash.postinst" etc.
+    /// Only the exact names are removed, as "python3.11.list" is not of "python3".
+    fn remove_info(prefix: &str) -> Result<(), Error> {
+        for ext in CONTROL_FILES {
+            let p = Path::new(DPKG_INFO).join(format!("{}.{}", prefix, ext));
+            if p.symlink_metadata().is_ok() {
+                fs::remove_file(p)?;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Returns true if the stanza describes a removed package.
+    /// Removed packages are "name" or "name:arch", as their control files are named.
+    fn is_removed(stanza: &str, removed: &HashSet<String>) -> bool {
+        let field = |name: &str| {
+            stanza.lines().find_map(|l| l.strip_prefix(name).and_then(|v| v.strip_prefix(':'))).unwrap_or_default().trim()
+        };
+        let (name, arch) = (field("Package"), field("Architecture"));
+
+        removed.contains(name) || removed.contains(&format!("{}:{}", name, arch))
+    }
+
+    /// Update the whole database
+    pub fn update(&self) -> Result<(), Error> {
+        let mut removed: HashSet<String> = HashSet::default();
+        let mut lists = fs::read_dir(DPKG_INFO)?
+            .filter_map(Result::ok)
+            .map(|e| e.path())
+            .filter(|p| p.extension().unwrap_or_default() == "list")
+            .collect::<Vec<PathBuf>>();
+        lists.sort();
+
+        for list in lists {
+            let prefix = list.file_stem().unwrap_or_default().to_str().unwrap_or_default().to_string();
+            let pkgname = prefix.split(':').next().unwrap_or_default().to_string();
+
+            if self.update_list(&list, &pkgname)? {
+                self.update_md5sums(&list.with_extension("md5sums"), &pkgname)?;
+            } else {
+                log::debug!("Removing package {} from dpkg database", prefix);
+                Self::remove_info(&prefix)?;
+                removed.insert(prefix);
+            }
+        }
+
+        if removed.is_empty() {
+            return Ok(());
+        }
+
+        let status = fs::read_to_string(DPKG_STATUS)?;
+        fs::write(
+            DPKG_STATUS,
+            status.split("\n\n").filter(|s| !Self::is_removed(s, &removed)).collect::<Vec<&str>>().join("\n\n"),
+        )?;
+        log::info!("Removed {} pruned packages from dpkg database", removed.len());
+
+        Ok(())
+    }
+}
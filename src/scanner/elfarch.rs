@@ -0,0 +1,212 @@
+/*
+Architecture definitions for the ELF objects.
+Used to pick library directories and dynamic loaders
+of an image, regardless of the host architecture.
+ */
+
+use super::elfrd::ElfInfo;
+use goblin::elf::header;
+use std::{fs, path::Path};
+
+/// ld.so.cache entry flags, required for a particular ABI
+const FLAG_REQUIRED_MASK: u32 = 0xff00;
+
+pub struct ElfArch {
+    machine: u16,
+    is_64: bool,
+    big_endian: bool,
+
+    /// Value of $PLATFORM dynamic string token
+    platform: &'static str,
+
+    /// Machine name, as reported by uname
+    uname: &'static str,
+
+    /// Debian-style multiarch triplets
+    triplets: &'static [&'static str],
+
+    /// Dynamic loader names
+    loaders: &'static [&'static str],
+
+    /// Accepted ld.so.cache ABI flags
+    cache_flags: &'static [u32],
+}
+
+const ARCHS: &[ElfArch] = &[
+    ElfArch {
+        machine: header::EM_X86_64,
+        is_64: true,
+        big_endian: false,
+        platform: "x86_64",
+        uname: "x86_64",
+        triplets: &["x86_64-linux-gnu"],
+        loaders: &["ld-linux-x86-64.so.2", "ld-musl-x86_64.so.1"],
+        cache_flags: &[0x0300],
+    },
+    ElfArch {
+        machine: header::EM_X86_64,
+        is_64: false,
+        big_endian: false,
+        platform: "x86_64",
+        uname: "x86_64",
+        triplets: &["x86_64-linux-gnux32"],
+        loaders: &["ld-linux-x32.so.2", "ld-musl-x32.so.1"],
+        cache_flags: &[0x0800],
+    },
+    ElfArch {
+        machine: header::EM_386,
+        is_64: false,
+        big_endian: false,
+        platform: "i686",
+        uname: "i686",
+        triplets: &["i386-linux-gnu"],
+        loaders: &["ld-linux.so.2", "ld-musl-i386.so.1"],
+        cache_flags: &[0x0000],
+    },
+    ElfArch {
+        machine: header::EM_AARCH64,
+        is_64: true,
+        big_endian: false,
+        platform: "aarch64",
+        uname: "aarch64",
+        triplets: &["aarch64-linux-gnu"],
+        loaders: &["ld-linux-aarch64.so.1", "ld-musl-aarch64.so.1"],
+        cache_flags: &[0x0a00],
+    },
+    ElfArch {
+        machine: header::EM_ARM,
+        is_64: false,
+        big_endian: false,
+        platform: "v7l",
+        uname: "armv7l",
+        triplets: &["arm-linux-gnueabihf", "arm-linux-gnueabi"],
+        loaders: &["ld-linux-armhf.so.3", "ld-linux.so.3", "ld-musl-armhf.so.1", "ld-musl-arm.so.1"],
+        cache_flags: &[0x0900, 0x0b00, 0x0000],
+    },
+    ElfArch {
+        machine: header::EM_RISCV,
+        is_64: true,
+        big_endian: false,
+        platform: "riscv64",
+        uname: "riscv64",
+        triplets: &["riscv64-linux-gnu"],
+        loaders: &["ld-linux-riscv64-lp64d.so.1", "ld-linux-riscv64-lp64.so.1", "ld-musl-riscv64.so.1"],
+        cache_flags: &[0x1000, 0x0f00],
+    },
+    ElfArch {
+        machine: header::EM_PPC64,
+        is_64: true,
+        big_endian: false,
+        platform: "powerpc64le",
+        uname: "ppc64le",
+        triplets: &["powerpc64le-linux-gnu"],
+        loaders: &["ld64.so.2", "ld-musl-powerpc64le.so.1"],
+        cache_flags: &[0x0500],
+    },
+    ElfArch {
+        machine: header::EM_PPC64,
+        is_64: true,
+        big_endian: true,
+        platform: "powerpc64",
+        uname: "ppc64",
+        triplets: &["powerpc64-linux-gnu"],
+        loaders: &["ld64.so.1", "ld-musl-powerpc64.so.1"],
+        cache_flags: &[0x0500],
+    },
+    ElfArch {
+        machine: header::EM_S390,
+        is_64: true,
+        big_endian: true,
+        platform: "s390x",
+        uname: "s390x",
+        triplets: &["s390x-linux-gnu"],
+        loaders: &["ld64.so.1", "ld-musl-s390x.so.1"],
+        cache_flags: &[0x0400],
+    },
+];
+
+impl ElfArch {
+    /// Architecture of an ELF object, if it is a known one
+    pub fn of(path: &Path) -> Option<&'static ElfArch> {
+        ElfInfo::read_header(path).and_then(|(machine, is_64, big_endian)| Self::find(machine, is_64, big_endian))
+    }
+
+    /// Detect architecture of the image under the root by its executables
+    pub fn detect(root: &Path) -> Option<&'static ElfArch> {
+        for d in ["usr/bin", "bin"] {
+            let mut bins = match fs::read_dir(root.join(d)) {
+                Ok(rd) => rd.filter_map(Result::ok).map(|e| e.path()).collect::<Vec<_>>(),
+                Err(_) => continue,
+            };
+            bins.sort();
+
+            // Symlinks might be absolute and point outside of the root
+            for p in bins.iter().filter(|p| matches!(p.symlink_metadata(), Ok(m) if m.is_file())) {
+                if let Some(arch) = Self::of(p) {
+                    return Some(arch);
+                }
+            }
+        }
+
+        None
+    }
+
+    /// Multiarch triplet of the image under the root: the one with the library directory
+    pub fn get_multiarch(&self, root: &Path) -> &'static str {
+        self.triplets
+            .iter()
+            .find(|t| ["usr/lib", "lib"].iter().any(|d| root.join(d).join(t).is_dir()))
+            .unwrap_or(&self.triplets[0])
+    }
+
+    /// Find architecture by ELF machine type, class and byte order
+    pub fn find(machine: u16, is_64: bool, big_endian: bool) -> Option<&'static ElfArch> {
+        ARCHS.iter().find(|a| a.machine == machine && a.is_64 == is_64 && a.big_endian == big_endian)
+    }
+
+    /// Returns true if the architecture is big-endian
+    pub fn is_big_endian(&self) -> bool {
+        self.big_endian
+    }
+
+    /// Architecture name, as in $PLATFORM
+    pub fn get_platform(&self) -> &'static str {
+        self.platform
+    }
+
+    /// Machine name, as reported by uname, e.g. "x86_64"
+    pub fn get_uname(&self) -> &'static str {
+        self.uname
+    }
+
+    /// Multiarch triplets
+    pub fn get_triplets(&self) -> &'static [&'static str] {
+        self.triplets
+    }
+
+    /// Known dynamic loader names for this architecture
+    pub fn get_loaders(&self) -> &'static [&'static str] {
+        self.loaders
+    }
+
+    /// Returns true if ld.so.cache entry flags are suitable for this architecture
+    pub fn is_cache_compatible(&self, flags: u32) -> bool {
+        self.cache_flags.contains(&(flags & FLAG_REQUIRED_MASK))
+    }
+
+    /// Default library directories, as ld.so would search them for this architecture
+    pub fn get_lib_dirs(&self) -> Vec<String> {
+        let mut dirs: Vec<String> = Vec::default();
+        for t in self.triplets {
+            dirs.push(format!("/lib/{}", t));
+            dirs.push(format!("/usr/lib/{}", t));
+        }
+
+        if self.is_64 {
+            dirs.extend(["/lib64".to_string(), "/usr/lib64".to_string()]);
+        }
+        dirs.extend(["/lib".to_string(), "/usr/lib".to_string()]);
+
+        dirs
+    }
+}
@@ -0,0 +1,252 @@
+/*
+ELF reader. Reads dynamic section of an ELF object
+without calling anything from the image.
+ */
+
+use goblin::elf::{header, section_header, sym, symver, Elf};
+use std::{
+    collections::HashSet,
+    fs::{self, File},
+    io::{Error, ErrorKind, Read},
+    path::{Path, PathBuf},
+};
+
+/// Dynamic linking information of an ELF object
+#[derive(Debug, Clone)]
+pub struct ElfInfo {
+    path: PathBuf,
+    machine: u16,
+    is_64: bool,
+    big_endian: bool,
+    interp: Option<String>,
+    soname: Option<String>,
+    needed: Vec<String>,
+    rpath: Vec<String>,
+    runpath: Vec<String>,
+    ver_needed: Vec<(String, Vec<String>)>,
+    ver_defined: Vec<String>,
+}
+
+impl ElfInfo {
+    /// Read an ELF object
+    pub fn read(path: &Path) -> Result<Self, Error> {
+        let data = fs::read(path)?;
+        let elf = Self::parse(path, &data)?;
+
+        Ok(ElfInfo {
+            path: path.to_path_buf(),
+            machine: elf.header.e_machine,
+            is_64: elf.is_64,
+            big_endian: !elf.little_endian,
+            interp: elf.interpreter.map(|s| s.to_string()),
+            soname: elf.soname.map(|s| s.to_string()),
+            needed: elf.libraries.iter().map(|s| s.to_string()).collect(),
+            rpath: Self::split_paths(&elf.rpaths),
+            runpath: Self::split_paths(&elf.runpaths),
+            ver_needed: Self::get_verneed(&elf),
+            ver_defined: Self::get_verdef(&elf),
+        })
+    }
+
+    /// Read version requirements (.gnu.version_r) as library soname and its version nodes
+    fn get_verneed(elf: &Elf) -> Vec<(String, Vec<String>)> {
+        let mut out: Vec<(String, Vec<String>)> = Vec::default();
+        if let Some(verneed) = &elf.verneed {
+            for vn in verneed.iter() {
+                if let Some(file) = elf.dynstrtab.get_at(vn.vn_file) {
+                    out.push((
+                        file.to_string(),
+                        vn.iter().filter_map(|vna| elf.dynstrtab.get_at(vna.vna_name)).map(|s| s.to_string()).collect(),
+                    ));
+                }
+            }
+        }
+
+        out
+    }
+
+    /// Read version definitions (.gnu.version_d), except the base one, which is the soname itself
+    fn get_verdef(elf: &Elf) -> Vec<String> {
+        let mut out: Vec<String> = Vec::default();
+        if let Some(verdef) = &elf.verdef {
+            for vd in verdef.iter().filter(|vd| vd.vd_flags & symver::VER_FLG_BASE == 0) {
+                if let Some(name) = vd.iter().next().and_then(|vda| elf.dynstrtab.get_at(vda.vda_name)) {
+                    out.push(name.to_string());
+                }
+            }
+        }
+
+        out
+    }
+
+    /// Read only ELF header and return machine type, class (64 bit or not) and byte order (big-endian or not).
+    /// Returns None if the file is not an ELF object.
+    pub fn read_header(path: &Path) -> Option<(u16, bool, bool)> {
+        let mut buff = [0u8; header::header64::SIZEOF_EHDR];
+        File::open(path).ok()?.read_exact(&mut buff).ok()?;
+
+        let hdr = Elf::parse_header(&buff).ok()?;
+        Some((
+            hdr.e_machine,
+            hdr.e_ident[header::EI_CLASS] == header::ELFCLASS64,
+            hdr.e_ident[header::EI_DATA] == header::ELFDATA2MSB,
+        ))
+    }
+
+    /// Parse ELF data
+    fn parse<'a>(path: &Path, data: &'a [u8]) -> Result<Elf<'a>, Error> {
+        Elf::parse(data).map_err(|err| Error::new(ErrorKind::InvalidData, format!("{:?} is not a valid ELF: {}", path, err)))
+    }
+
+    /// DT_RPATH and DT_RUNPATH entries are colon-separated lists
+    fn split_paths(paths: &[&str]) -> Vec<String> {
+        paths.iter().flat_map(|p| p.split(':')).filter(|p| !p.is_empty()).map(|p| p.to_string()).collect()
+    }
+
+    /// Path of the object
+    pub fn get_path(&self) -> &Path {
+        &self.path
+    }
+
+    /// ELF machine type (e_machine)
+    pub fn get_machine(&self) -> u16 {
+        self.machine
+    }
+
+    /// Returns true if the object is ELFCLASS64
+    pub fn is_64(&self) -> bool {
+        self.is_64
+    }
+
+    /// Returns true if the object is ELFDATA2MSB
+    pub fn is_big_endian(&self) -> bool {
+        self.big_endian
+    }
+
+    /// Program interpreter (PT_INTERP), i.e. the dynamic loader
+    pub fn get_interp(&self) -> Option<&String> {
+        self.interp.as_ref()
+    }
+
+    /// DT_SONAME, if any
+    pub fn get_soname(&self) -> Option<&String> {
+        self.soname.as_ref()
+    }
+
+    /// Required version nodes, per library soname
+    pub fn get_ver_needed(&self) -> &Vec<(String, Vec<String>)> {
+        &self.ver_needed
+    }
+
+    /// Version nodes, defined by this object
+    pub fn get_ver_defined(&self) -> &Vec<String> {
+        &self.ver_defined
+    }
+
+    /// DT_NEEDED entries
+    pub fn get_needed(&self) -> &Vec<String> {
+        &self.needed
+    }
+
+    /// DT_RPATH entries
+    pub fn get_rpath(&self) -> &Vec<String> {
+        &self.rpath
+    }
+
+    /// DT_RUNPATH entries
+    pub fn get_runpath(&self) -> &Vec<String> {
+        &self.runpath
+    }
+}
+
+/// Dynamic symbols of an ELF object
+pub struct ElfSymbols {
+    imported: HashSet<String>,
+    exported: HashSet<String>,
+}
+
+impl ElfSymbols {
+    /// Read dynamic symbol table of an ELF object
+    pub fn read(path: &Path) -> Result<Self, Error> {
+        let data = fs::read(path)?;
+        let elf = ElfInfo::parse(path, &data)?;
+        let mut es = ElfSymbols { imported: HashSet::default(), exported: HashSet::default() };
+
+        for s in elf.dynsyms.iter() {
+            let name = match elf.dynstrtab.get_at(s.st_name) {
+                Some(name) if !name.is_empty() => name.to_string(),
+                _ => continue,
+            };
+
+            if !matches!(s.st_bind(), sym::STB_GLOBAL | sym::STB_WEAK | sym::STB_GNU_UNIQUE) {
+                continue;
+            }
+
+            if s.st_shndx == section_header::SHN_UNDEF as usize {
+                es.imported.insert(name);
+            } else if matches!(s.st_visibility(), sym::STV_DEFAULT | sym::STV_PROTECTED)
+                && !matches!(s.st_type(), sym::STT_SECTION | sym::STT_FILE)
+            {
+                es.exported.insert(name);
+            }
+        }
+
+        Ok(es)
+    }
+
+    /// Undefined symbols, those are expected from other objects
+    pub fn get_imported(&self) -> &HashSet<String> {
+        &self.imported
+    }
+
+    /// Symbols, defined by this object and visible to others
+    pub fn get_exported(&self) -> &HashSet<String> {
+        &self.exported
+    }
+}
+
+/// Printable strings of the read-only data of an ELF object
+pub struct ElfStrings {
+    strings: Vec<String>,
+}
+
+impl ElfStrings {
+    /// Read NUL-terminated strings from .rodata section
+    pub fn read(path: &Path, min_len: usize) -> Result<Self, Error> {
+        let data = fs::read(path)?;
+        let elf = ElfInfo::parse(path, &data)?;
+        let mut es = ElfStrings { strings: Vec::default() };
+
+        for sh in elf.section_headers.iter().filter(|sh| elf.shdr_strtab.get_at(sh.sh_name) == Some(".rodata")) {
+            if sh.sh_type == section_header::SHT_NOBITS {
+                continue;
+            }
+
+            // Section header might be broken and point outside of the file
+            let rodata = sh
+                .sh_offset
+                .checked_add(sh.sh_size)
+                .and_then(|end| data.get(usize::try_from(sh.sh_offset).ok()?..usize::try_from(end).ok()?));
+            let rodata = match rodata {
+                Some(rodata) => rodata,
+                None => {
+                    log::warn!("Section .rodata of {:?} is out of the file bounds", path);
+                    continue;
+                }
+            };
+
+            for s in rodata.split(|b| *b == 0) {
+                if s.len() >= min_len && s.iter().all(|b| b.is_ascii_graphic() || *b == b' ') {
+                    es.strings.push(String::from_utf8_lossy(s).to_string());
+                }
+            }
+        }
+
+        Ok(es)
+    }
+
+    /// Get strings
+    pub fn get_strings(&self) -> &Vec<String> {
+        &self.strings
+    }
+}
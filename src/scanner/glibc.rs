@@ -0,0 +1,188 @@
+/*
+Implicit runtime dependencies of glibc.
+
+glibc loads NSS modules, gconv modules for iconv(3) and libgcc_s
+for the thread cancellation with dlopen(), so they never appear
+in DT_NEEDED of any object.
+ */
+
+use super::{
+    binlib::ElfScanner,
+    elfarch::ElfArch,
+    elfrd::{ElfInfo, ElfSymbols},
+    general::Scanner,
+    ldresolv::LdResolver,
+};
+use std::{
+    collections::HashSet,
+    fs,
+    path::{Path, PathBuf},
+};
+
+/// NSS configuration
+const NSS_CONF: &str = "/etc/nsswitch.conf";
+
+/// Symbols, those are using gconv modules
+const GCONV_SYMBOLS: &[&str] = &["iconv_open", "__gconv_open"];
+
+/// Unwinder library, needed by pthread_cancel() and pthread_exit()
+const LIBGCC_S: &str = "libgcc_s.so.1";
+
+pub struct GlibcDeps<'a> {
+    libc: ElfInfo,
+    resolver: &'a LdResolver,
+    elfscan: ElfScanner<'a>,
+}
+
+impl<'a> GlibcDeps<'a> {
+    /// Find libc of the target architecture among the kept ELF objects.
+    /// Returns None if there is no glibc.
+    pub fn new(resolver: &'a LdResolver, paths: &HashSet<PathBuf>, arch: Option<&ElfArch>) -> Option<Self> {
+        let mut libcs = paths
+            .iter()
+            .filter(|p| p.file_name().unwrap_or_default().to_str().unwrap_or_default().starts_with("libc.so."))
+            .collect::<Vec<_>>();
+        libcs.sort();
+
+        // Multilib images might have several of them
+        let libc = libcs.into_iter().find(|p| match arch {
+            Some(arch) => matches!(ElfArch::of(p), Some(a) if std::ptr::eq(a, arch)),
+            None => true,
+        })?;
+        let libc = ElfInfo::read(&fs::canonicalize(libc).unwrap_or(libc.to_owned())).ok()?;
+
+        // musl has no NSS, gconv or libgcc_s loading
+        if !libc.get_ver_defined().iter().any(|v| v.starts_with("GLIBC_")) {
+            return None;
+        }
+
+        log::debug!("Found glibc at {:?}", libc.get_path());
+        Some(GlibcDeps { libc, resolver, elfscan: ElfScanner::new(resolver) })
+    }
+
+    /// Get a module with all its dependencies
+    fn with_deps(&mut self, module: &Path) -> Vec<PathBuf> {
+        let mut out = vec![module.to_path_buf()];
+        if let Ok(p) = fs::canonicalize(module) {
+            out.push(p);
+        }
+        out.extend(self.elfscan.scan(module.to_path_buf()).get_paths().to_owned());
+
+        out
+    }
+
+    /// Get NSS services from nsswitch.conf, e.g. "files", "systemd", "dns"
+    fn get_nss_services(&self) -> Vec<String> {
+        let mut services: Vec<String> = Vec::default();
+        for l in fs::read_to_string(NSS_CONF).unwrap_or_default().lines() {
+            let l = l.split_once('#').map(|(l, _)| l).unwrap_or(l);
+            if let Some((_, srv)) = l.split_once(':') {
+                // Skip actions, like "[NOTFOUND=return]"
+                for s in srv.split_whitespace().filter(|s| !s.starts_with('[')) {
+                    if !services.contains(&s.to_string()) {
+                        services.push(s.to_string());
+                    }
+                }
+            }
+        }
+
+        services
+    }
+
+    /// Get NSS modules, configured in nsswitch.conf, with their dependencies
+    pub fn get_nss(&mut self) -> Vec<PathBuf> {
+        let mut out: Vec<PathBuf> = Vec::default();
+        if !Path::new(NSS_CONF).exists() {
+            return out;
+        }
+
+        out.push(PathBuf::from(NSS_CONF));
+        for srv in self.get_nss_services() {
+            match self.resolver.resolve(&self.libc, &format!("libnss_{}.so.2", srv), &[]) {
+                Some(m) => {
+                    log::debug!("Keeping NSS module {:?}", m);
+                    out.extend(self.with_deps(&m));
+                }
+                None => {
+                    // Since glibc 2.34 "files" and "dns" are built into libc,
+                    // and the rest is just skipped by glibc, if not installed.
+                    log::debug!("NSS module for \"{}\" service was not found", srv);
+                }
+            }
+        }
+
+        out
+    }
+
+    /// Get gconv directory: next to libc, or in a library directory of its architecture,
+    /// as with non-merged /usr libc is in /lib, but gconv is in /usr/lib.
+    fn get_gconv_dir(&self) -> Option<PathBuf> {
+        let mut dirs: Vec<PathBuf> = self.libc.get_path().parent().map(|d| d.join("gconv")).into_iter().collect();
+        if let Some(arch) = ElfArch::find(self.libc.get_machine(), self.libc.is_64(), self.libc.is_big_endian()) {
+            dirs.extend(arch.get_lib_dirs().iter().map(|d| Path::new(d).join("gconv")));
+        }
+
+        dirs.into_iter().find(|d| d.is_dir())
+    }
+
+    /// Get gconv configuration: gconv-modules, gconv-modules.d/*.conf and the cache
+    fn get_gconv_conf(&self, dir: &Path) -> Vec<PathBuf> {
+        let mut out: Vec<PathBuf> = vec![dir.join("gconv-modules"), dir.join("gconv-modules.cache")];
+        if let Ok(rd) = fs::read_dir(dir.join("gconv-modules.d")) {
+            out.extend(rd.filter_map(Result::ok).map(|e| e.path()).filter(|p| p.extension().unwrap_or_default() == "conf"));
+        }
+        out.retain(|p| p.is_file());
+
+        out
+    }
+
+    /// Get gconv modules, if any of the objects is calling iconv
+    pub fn get_gconv(&mut self, objects: &HashSet<PathBuf>) -> Vec<PathBuf> {
+        let mut out: Vec<PathBuf> = Vec::default();
+        let dir = match self.get_gconv_dir() {
+            Some(dir) => dir,
+            None => return out,
+        };
+
+        if !objects.iter().filter(|p| ElfInfo::read_header(p).is_some()).any(|p| {
+            ElfSymbols::read(p).map(|s| GCONV_SYMBOLS.iter().any(|sym| s.get_imported().contains(*sym))).unwrap_or(false)
+        }) {
+            log::debug!("No objects are calling iconv, gconv modules are skipped");
+            return out;
+        }
+
+        // Module lines are: "module FROM TO FILE COST"
+        let mut modules: HashSet<String> = HashSet::default();
+        for conf in self.get_gconv_conf(&dir) {
+            out.push(conf.to_owned());
+            if conf.file_name().unwrap_or_default() == "gconv-modules.cache" {
+                continue;
+            }
+
+            for l in fs::read_to_string(&conf).unwrap_or_default().lines() {
+                let f = l.split_whitespace().collect::<Vec<&str>>();
+                if f.len() >= 4 && f[0] == "module" && f[3] != "INTERNAL" {
+                    modules.insert(format!("{}.so", f[3]));
+                }
+            }
+        }
+
+        log::debug!("Keeping {} gconv modules", modules.len());
+        for m in modules.iter().map(|m| if m.starts_with('/') { PathBuf::from(m) } else { dir.join(m) }).filter(|p| p.exists()) {
+            out.extend(self.with_deps(&m));
+        }
+
+        out
+    }
+
+    /// Get libgcc_s with its dependencies
+    pub fn get_libgcc(&mut self) -> Vec<PathBuf> {
+        match self.resolver.resolve(&self.libc, LIBGCC_S, &[]) {
+            Some(p) => self.with_deps(&p),
+            None => {
+                log::debug!("{} was not found", LIBGCC_S);
+                vec![]
+            }
+        }
+    }
+}
@@ -0,0 +1,292 @@
+/*
+Dynamic loader emulation. Resolves sonames to the absolute paths
+the same way as ld.so does: DT_RPATH, DT_RUNPATH, ld.so.cache,
+ld.so.conf and default system directories.
+ */
+
+use super::{elfarch::ElfArch, elfrd::ElfInfo};
+use std::{
+    collections::HashSet,
+    fs,
+    path::{Path, PathBuf},
+};
+
+const LD_SO_CONF: &str = "/etc/ld.so.conf";
+const LD_SO_CACHE: &str = "/etc/ld.so.cache";
+const LD_CACHE_MAGIC: &[u8] = b"glibc-ld.so.cache1.1";
+
+/// Default trusted directories
+const LD_DEFAULT_DIRS: &[&str] = &["/lib", "/usr/lib"];
+const LD_DEFAULT_DIRS_64: &[&str] = &["/lib64", "/usr/lib64", "/lib", "/usr/lib"];
+
+pub struct LdResolver {
+    conf_dirs: Vec<PathBuf>,
+    cache: Vec<(u32, String, PathBuf)>,
+}
+
+impl LdResolver {
+    pub fn new() -> Self {
+        let mut ldr = LdResolver { conf_dirs: Vec::default(), cache: Vec::default() };
+        ldr.conf_dirs = ldr.load_conf(Path::new(LD_SO_CONF), &mut HashSet::default());
+        ldr.load_cache();
+
+        ldr
+    }
+
+    /// Read ld.so.conf, following "include" directives
+    fn load_conf(&self, conf: &Path, visited: &mut HashSet<PathBuf>) -> Vec<PathBuf> {
+        let mut dirs: Vec<PathBuf> = Vec::default();
+        if !visited.insert(conf.to_path_buf()) {
+            return dirs;
+        }
+
+        let data = match fs::read_to_string(conf) {
+            Ok(data) => data,
+            Err(err) => {
+                log::debug!("Unable to read {:?}: {}", conf, err);
+                return dirs;
+            }
+        };
+
+        for l in data.lines() {
+            let l = l.split_once('#').map(|(l, _)| l).unwrap_or(l).trim();
+            if l.is_empty() {
+                continue;
+            }
+
+            if let Some(inc) = l.strip_prefix("include").filter(|s| s.starts_with(char::is_whitespace)) {
+                for pattern in inc.split_whitespace() {
+                    // Relative includes are relative to the directory of the config
+                    let pattern = conf.parent().unwrap_or(Path::new("/")).join(pattern);
+                    if let Ok(paths) = glob::glob(pattern.to_str().unwrap_or_default()) {
+                        let mut paths = paths.filter_map(Result::ok).collect::<Vec<PathBuf>>();
+                        paths.sort();
+                        for p in paths {
+                            dirs.extend(self.load_conf(&p, visited));
+                        }
+                    }
+                }
+            } else if l.starts_with("hwcap") {
+                continue;
+            } else {
+                // Old-style "dir=TYPE" entries are still allowed
+                let d = PathBuf::from(l.split_once('=').map(|(d, _)| d).unwrap_or(l).trim());
+                if !dirs.contains(&d) {
+                    dirs.push(d);
+                }
+            }
+        }
+
+        dirs
+    }
+
+    fn u32_at(data: &[u8], offset: usize, big_endian: bool) -> Option<u32> {
+        let b: [u8; 4] = data.get(offset..offset + 4)?.try_into().ok()?;
+        Some(if big_endian { u32::from_be_bytes(b) } else { u32::from_le_bytes(b) })
+    }
+
+    fn str_at(data: &[u8], offset: usize) -> Option<String> {
+        let s = data.get(offset..)?;
+        let end = s.iter().position(|b| *b == 0)?;
+        Some(String::from_utf8_lossy(&s[..end]).to_string())
+    }
+
+    /// Read ld.so.cache. It is written by ldconfig in the byte order of the image.
+    fn load_cache(&mut self) {
+        let data = match fs::read(LD_SO_CACHE) {
+            Ok(data) => data,
+            Err(err) => {
+                log::debug!("Unable to read {}: {}", LD_SO_CACHE, err);
+                return;
+            }
+        };
+
+        let big_endian = ElfArch::detect(Path::new("/")).map(|a| a.is_big_endian()).unwrap_or(cfg!(target_endian = "big"));
+        match Self::parse_cache(&data, big_endian) {
+            Some(cache) => self.cache = cache,
+            None => log::debug!("{} has unsupported format", LD_SO_CACHE),
+        }
+    }
+
+    /// Parse ld.so.cache in "new" format (glibc 2.x), either standalone or after the old format header.
+    /// Returns entry flags, sonames and paths.
+    fn parse_cache(data: &[u8], big_endian: bool) -> Option<Vec<(u32, String, PathBuf)>> {
+        let start = data.windows(LD_CACHE_MAGIC.len()).position(|w| w == LD_CACHE_MAGIC)?;
+
+        // Header: magic[20], nlibs, len_strings, flags[4], extension_offset, unused[3].
+        // Broken or foreign cache must not claim more entries than it has.
+        let cache = &data[start..];
+        let nlibs = (Self::u32_at(cache, 20, big_endian)? as usize).min(cache.len().saturating_sub(48) / 24);
+
+        // Entries: flags, key, value, osversion, hwcap (u64).
+        // Keys and values are offsets of the strings from the start of the header.
+        let mut out: Vec<(u32, String, PathBuf)> = Vec::default();
+        for i in 0..nlibs {
+            let e = 48 + i * 24;
+            let field = |o: usize| Self::u32_at(cache, e + o, big_endian);
+            if let (Some(f), Some(k), Some(v)) = (field(0), field(4), field(8)) {
+                if let (Some(soname), Some(path)) = (Self::str_at(cache, k as usize), Self::str_at(cache, v as usize)) {
+                    out.push((f, soname, PathBuf::from(path)));
+                }
+            }
+        }
+
+        Some(out)
+    }
+
+    /// Expand dynamic string tokens ($ORIGIN, $LIB, $PLATFORM)
+    fn expand(&self, dir: &str, origin: &Path, obj: &ElfInfo) -> PathBuf {
+        let mut lib = (if obj.is_64() { "lib64" } else { "lib" }).to_string();
+        let mut out =
+            dir.replace("${ORIGIN}", origin.to_str().unwrap_or_default()).replace("$ORIGIN", origin.to_str().unwrap_or_default());
+
+        if let Some(arch) = ElfArch::find(obj.get_machine(), obj.is_64(), obj.is_big_endian()) {
+            // Multiarch layout has $LIB pointing to its triplet
+            if let Some(t) = arch.get_triplets().iter().find(|t| Path::new("/lib").join(t).is_dir()) {
+                lib = format!("lib/{}", t);
+            }
+            out = out.replace("${PLATFORM}", arch.get_platform()).replace("$PLATFORM", arch.get_platform());
+        }
+
+        PathBuf::from(out.replace("${LIB}", &lib).replace("$LIB", &lib))
+    }
+
+    /// Expand RPATH or RUNPATH entries of an object
+    pub fn search_dirs(&self, obj: &ElfInfo, dirs: &[String]) -> Vec<PathBuf> {
+        let origin = obj.get_path().parent().unwrap_or(Path::new("/")).to_path_buf();
+        dirs.iter().map(|d| self.expand(d, &origin, obj)).collect()
+    }
+
+    /// Default library directories for the architecture of the object
+    fn default_dirs(&self, obj: &ElfInfo) -> Vec<PathBuf> {
+        match ElfArch::find(obj.get_machine(), obj.is_64(), obj.is_big_endian()) {
+            Some(arch) => arch.get_lib_dirs().iter().map(PathBuf::from).collect(),
+            None => (if obj.is_64() { LD_DEFAULT_DIRS_64 } else { LD_DEFAULT_DIRS }).iter().map(PathBuf::from).collect(),
+        }
+    }
+
+    /// Find a dynamic loader for the architecture of the object
+    pub fn find_loader(&self, obj: &ElfInfo) -> Option<PathBuf> {
+        let arch = ElfArch::find(obj.get_machine(), obj.is_64(), obj.is_big_endian())?;
+        for ldname in arch.get_loaders() {
+            if let Some(p) = self.find_in(obj, ldname, &self.default_dirs(obj)) {
+                return Some(p);
+            }
+        }
+
+        None
+    }
+
+    /// Check if the candidate library is suitable for the requester,
+    /// as ld.so skips libraries of other machine type or class.
+    fn is_compatible(&self, obj: &ElfInfo, candidate: &Path) -> bool {
+        match ElfInfo::read_header(candidate) {
+            Some((machine, is_64, big_endian)) => {
+                machine == obj.get_machine() && is_64 == obj.is_64() && big_endian == obj.is_big_endian()
+            }
+            None => false,
+        }
+    }
+
+    fn find_in(&self, obj: &ElfInfo, soname: &str, dirs: &[PathBuf]) -> Option<PathBuf> {
+        for d in dirs {
+            let p = d.join(soname);
+            if p.exists() && self.is_compatible(obj, &p) {
+                return Some(p);
+            }
+        }
+
+        None
+    }
+
+    /// Resolve a soname, needed by the object, to an absolute path.
+    ///
+    /// Inherited RPATH is a list of already expanded DT_RPATH directories
+    /// of the objects which loaded this one (up to the main executable).
+    pub fn resolve(&self, obj: &ElfInfo, soname: &str, inherited_rpath: &[PathBuf]) -> Option<PathBuf> {
+        if soname.contains('/') {
+            let p = self.expand(soname, obj.get_path().parent().unwrap_or(Path::new("/")), obj);
+            return if p.exists() { Some(p) } else { None };
+        }
+
+        // DT_RPATH is used only if there is no DT_RUNPATH
+        if obj.get_runpath().is_empty() {
+            let mut dirs = self.search_dirs(obj, obj.get_rpath());
+            dirs.extend(inherited_rpath.iter().cloned());
+            if let Some(p) = self.find_in(obj, soname, &dirs) {
+                return Some(p);
+            }
+        } else if let Some(p) = self.find_in(obj, soname, &self.search_dirs(obj, obj.get_runpath())) {
+            return Some(p);
+        }
+
+        // Cache may contain libraries for several architectures
+        let arch = ElfArch::find(obj.get_machine(), obj.is_64(), obj.is_big_endian());
+        for (flags, name, p) in &self.cache {
+            if name == soname
+                && arch.map(|a| a.is_cache_compatible(*flags)).unwrap_or(true)
+                && p.exists()
+                && self.is_compatible(obj, p)
+            {
+                return Some(p.to_owned());
+            }
+        }
+
+        // Cache might be stale or missing, e.g. ldconfig was never called on the image.
+        let mut dirs = self.conf_dirs.clone();
+        dirs.extend(self.default_dirs(obj));
+
+        self.find_in(obj, soname, &dirs)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{LdResolver, LD_CACHE_MAGIC};
+    use std::path::PathBuf;
+
+    /// Build a cache in "new" format with the entries of the given byte order
+    fn cache(entries: &[(u32, &str, &str)], big_endian: bool) -> Vec<u8> {
+        let u32b = |v: u32| if big_endian { v.to_be_bytes() } else { v.to_le_bytes() };
+        let strings_at = 48 + entries.len() * 24;
+        let mut strings: Vec<u8> = Vec::default();
+        let mut data: Vec<u8> = LD_CACHE_MAGIC.to_vec();
+        data.extend(u32b(entries.len() as u32));
+        data.extend([0u8; 24]);
+        for (flags, soname, path) in entries {
+            let k = strings_at + strings.len();
+            strings.extend(soname.bytes().chain([0]));
+            let v = strings_at + strings.len();
+            strings.extend(path.bytes().chain([0]));
+            data.extend(u32b(*flags));
+            data.extend(u32b(k as u32));
+            data.extend(u32b(v as u32));
+            data.extend([0u8; 12]);
+        }
+        data.extend(strings);
+        data
+    }
+
+    #[test]
+    fn test_parse_cache() {
+        let entries = [(0x0303, "libc.so.6", "/lib/x86_64-linux-gnu/libc.so.6"), (0x0303, "libz.so.1", "/lib/libz.so.1")];
+        for big_endian in [false, true] {
+            let parsed = LdResolver::parse_cache(&cache(&entries, big_endian), big_endian).unwrap();
+            assert_eq!(parsed, entries.iter().map(|(f, s, p)| (*f, s.to_string(), PathBuf::from(p))).collect::<Vec<_>>());
+        }
+    }
+
+    #[test]
+    fn test_parse_cache_bounds() {
+        // Read in the wrong byte order, the number of entries is huge
+        let data = cache(&[(0x0303, "libc.so.6", "/lib/libc.so.6")], true);
+        assert!(LdResolver::parse_cache(&data, false).unwrap().len() <= 1);
+
+        // Old format header before the new one
+        let mut data = b"ld.so-1.7.0\0\0\0\0\0".to_vec();
+        data.extend(cache(&[(0x0303, "libc.so.6", "/lib/libc.so.6")], false));
+        assert_eq!(LdResolver::parse_cache(&data, false).unwrap().len(), 1);
+
+        assert!(LdResolver::parse_cache(b"garbage", false).is_none());
+    }
+}
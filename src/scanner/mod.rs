@@ -1,9 +1,29 @@
+pub mod apkdb;
+pub mod backend;
 pub mod binlib;
+pub mod dbftrace;
+pub mod dbpkg;
 pub mod debftrace;
 pub mod debpkg;
 mod deltafinder;
+pub mod dlscan;
 pub(crate) mod dlst;
+pub mod dpkgstatus;
+pub mod dpkgupdate;
+pub mod elfarch;
+mod elfrd;
 pub mod general;
-pub mod rpmftrace;
+pub mod glibc;
+pub mod ldresolv;
+pub mod pacmandb;
+pub mod pkgdb;
+pub mod pldeps;
+pub mod pydeps;
+pub mod rpmdb;
+pub mod shdeps;
+pub mod shebang;
+pub mod symusage;
+pub mod tracedb;
 pub mod tracedeb;
 pub mod traceitf;
+pub mod verchk;
@@ -0,0 +1,199 @@
+/*
+Arch Linux pacman database reader.
+
+Each installed package has its own directory in the local database
+with "desc" and "files" entries. Both are sections of "%NAME%" headers,
+followed by the values, one per line, until a blank line.
+ */
+
+use super::pkgdb::{DbPackage, PackageDb};
+use std::{
+    collections::HashMap,
+    fs,
+    io::Error,
+    path::{Path, PathBuf},
+};
+
+/// Local database of the installed packages
+const PACMAN_LOCAL: &str = "/var/lib/pacman/local";
+
+/// Installed package, as recorded in the pacman database
+#[derive(Default)]
+pub struct PacmanPackage {
+    name: String,
+    files: Vec<PathBuf>,
+    depends: Vec<String>,
+    optdepends: Vec<String>,
+    provides: Vec<String>,
+}
+
+impl DbPackage for PacmanPackage {
+    fn get_name(&self) -> &str {
+        &self.name
+    }
+
+    /// Files of the package, without directories
+    fn get_files(&self) -> &[PathBuf] {
+        &self.files
+    }
+
+    fn get_provides(&self) -> &[String] {
+        &self.provides
+    }
+
+    /// Dependencies are package names, virtual names or sonames.
+    /// Optional dependencies are not installed by default, so they are followed as the suggested ones.
+    fn get_deps(&self) -> Vec<(&'static str, &'static str, &[String])> {
+        vec![("Depends", "depends", &self.depends), ("Optdepends", "suggests", &self.optdepends)]
+    }
+}
+
+pub struct PacmanDb {
+    packages: Vec<PacmanPackage>,
+}
+
+impl PacmanDb {
+    /// Returns true if pacman is the package manager of the image
+    pub fn is_system_db() -> bool {
+        Path::new(PACMAN_LOCAL).is_dir()
+    }
+
+    /// Strip version constraint, e.g. "glibc>=2.38" or "libreadline.so=8-64"
+    fn strip_version(s: &str) -> &str {
+        s.split(['<', '>', '=']).next().unwrap_or_default()
+    }
+
+    /// Parse sections of an entry
+    fn get_sections(entry: &Path) -> HashMap<String, Vec<String>> {
+        let mut out: HashMap<String, Vec<String>> = HashMap::default();
+        let mut section = String::default();
+        for l in fs::read_to_string(entry).unwrap_or_default().lines() {
+            if l.starts_with('%') && l.ends_with('%') {
+                section = l.trim_matches('%').to_string();
+            } else if l.is_empty() {
+                section.clear();
+            } else if !section.is_empty() {
+                out.entry(section.to_owned()).or_default().push(l.to_string());
+            }
+        }
+
+        out
+    }
+
+    /// Read a package from its database directory
+    fn read_package(dir: &Path) -> Option<PacmanPackage> {
+        let mut desc = Self::get_sections(&dir.join("desc"));
+        let mut files = Self::get_sections(&dir.join("files"));
+
+        let mut take = |s: &str| desc.remove(s).unwrap_or_default();
+        let pkg = PacmanPackage {
+            name: take("NAME").into_iter().next()?,
+            depends: take("DEPENDS").iter().map(|d| Self::strip_version(d).to_string()).collect(),
+            // Optional dependencies are followed by a description, e.g. "python: for scripts"
+            optdepends: take("OPTDEPENDS")
+                .iter()
+                .map(|d| Self::strip_version(d.split(':').next().unwrap_or_default()).trim().to_string())
+                .collect(),
+            provides: take("PROVIDES").iter().map(|p| Self::strip_version(p).to_string()).collect(),
+            // Paths are relative to the root, directories are ending with a slash
+            files: files
+                .remove("FILES")
+                .unwrap_or_default()
+                .iter()
+                .filter(|f| !f.ends_with('/'))
+                .map(|f| Path::new("/").join(f))
+                .collect(),
+        };
+
+        Some(pkg)
+    }
+}
+
+impl PackageDb for PacmanDb {
+    type Package = PacmanPackage;
+    const NAME: &'static str = "pacman";
+
+    /// Read all installed packages
+    fn read() -> Result<Self, Error> {
+        let mut packages: Vec<PacmanPackage> = Vec::default();
+        for d in fs::read_dir(PACMAN_LOCAL)?.filter_map(Result::ok).map(|e| e.path()).filter(|p| p.is_dir()) {
+            match Self::read_package(&d) {
+                Some(pkg) => packages.push(pkg),
+                None => log::warn!("Broken pacman database entry at {:?}", d),
+            }
+        }
+
+        log::debug!("Found {} pacman packages", packages.len());
+        Ok(PacmanDb { packages })
+    }
+
+    fn get_db_paths() -> Vec<PathBuf> {
+        vec![PathBuf::from(PACMAN_LOCAL)]
+    }
+
+    fn get_packages(&self) -> &[PacmanPackage] {
+        &self.packages
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::PacmanDb;
+    use crate::scanner::pkgdb::DbPackage;
+    use std::{fs, path::PathBuf};
+
+    const DESC: &str = "\
+%NAME%
+bash
+
+%VERSION%
+5.2.026-2
+
+%DEPENDS%
+readline>=7.0
+glibc
+ncurses
+
+%OPTDEPENDS%
+bash-completion: for tab completion
+
+%PROVIDES%
+sh
+libreadline.so=8-64
+";
+
+    const FILES: &str = "\
+%FILES%
+etc/
+etc/bash.bashrc
+usr/
+usr/bin/
+usr/bin/bash
+
+%BACKUP%
+etc/bash.bashrc\tc2d1ad4d9b5ef16e1c7bb3d0fae03a1c
+";
+
+    #[test]
+    fn test_read_package() {
+        let dir = std::env::temp_dir().join(format!("mezzotint-pacman-{}", std::process::id()));
+        let (bash, broken) = (dir.join("bash-5.2.026-2"), dir.join("broken-1.0-1"));
+        fs::create_dir_all(&bash).unwrap();
+        fs::create_dir_all(&broken).unwrap();
+        fs::write(bash.join("desc"), DESC).unwrap();
+        fs::write(bash.join("files"), FILES).unwrap();
+        fs::write(broken.join("desc"), "%VERSION%\n1.0-1\n").unwrap();
+
+        let pkg = PacmanDb::read_package(&bash);
+        let none = PacmanDb::read_package(&broken);
+        fs::remove_dir_all(&dir).unwrap();
+
+        let pkg = pkg.unwrap();
+        assert_eq!(pkg.get_name(), "bash");
+        assert_eq!(pkg.get_files(), &[PathBuf::from("/etc/bash.bashrc"), PathBuf::from("/usr/bin/bash")]);
+        assert_eq!(pkg.get_provides(), &["sh", "libreadline.so"]);
+        assert_eq!(pkg.depends, vec!["readline", "glibc", "ncurses"]);
+        assert_eq!(pkg.optdepends, vec!["bash-completion"]);
+        assert!(none.is_none());
+    }
+}
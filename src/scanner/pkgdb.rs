@@ -0,0 +1,68 @@
+/*
+Package databases, read natively from the image.
+
+Backends of rpm, apk and pacman only parse their databases into
+the installed packages. Ownership, contents and dependencies are
+answered by the same scanner and tracers for all of them.
+ */
+
+use std::{collections::HashMap, io::Error, path::PathBuf};
+
+/// Installed package, as recorded in the package database
+pub trait DbPackage {
+    /// Name of the package
+    fn get_name(&self) -> &str;
+
+    /// Files of the package
+    fn get_files(&self) -> &[PathBuf];
+
+    /// Names, provided by the package besides its own name
+    fn get_provides(&self) -> &[String];
+
+    /// Dependencies: relation type, relation of the policy and the requirements
+    fn get_deps(&self) -> Vec<(&'static str, &'static str, &[String])>;
+}
+
+pub trait PackageDb: Sized {
+    type Package: DbPackage;
+
+    /// Name of the package manager, e.g. "rpm"
+    const NAME: &'static str;
+
+    /// Read all installed packages
+    fn read() -> Result<Self, Error>;
+
+    /// Get locations of the database in the image
+    fn get_db_paths() -> Vec<PathBuf>;
+
+    /// Get all installed packages
+    fn get_packages(&self) -> &[Self::Package];
+
+    /// Get package by its name
+    fn get_package(&self, name: &str) -> Option<&Self::Package> {
+        self.get_packages().iter().find(|p| p.get_name() == name)
+    }
+
+    /// Map of all packaged files to their package names
+    fn get_file_owners(&self) -> HashMap<PathBuf, String> {
+        let mut out: HashMap<PathBuf, String> = HashMap::default();
+        for p in self.get_packages() {
+            out.extend(p.get_files().iter().map(|f| (f.to_owned(), p.get_name().to_string())));
+        }
+        out
+    }
+
+    /// Map of all provided names to the names of the packages, providing them
+    fn get_providers(&self) -> HashMap<String, Vec<String>> {
+        let mut out: HashMap<String, Vec<String>> = HashMap::default();
+        for p in self.get_packages() {
+            for c in p.get_provides().iter().map(String::as_str).chain([p.get_name()]) {
+                let pkgs = out.entry(c.to_string()).or_default();
+                if !pkgs.iter().any(|n| n == p.get_name()) {
+                    pkgs.push(p.get_name().to_string());
+                }
+            }
+        }
+        out
+    }
+}
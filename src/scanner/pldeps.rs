@@ -0,0 +1,337 @@
+/*
+Perl module dependency tracer.
+
+Follows "use" and "require" statements of a Perl target
+through the @INC directories of the interpreter inside the image.
+ */
+
+use super::{
+    binlib::ElfScanner,
+    elfarch::ElfArch,
+    elfrd::ElfInfo,
+    general::{Scanner, ScannerResult},
+    ldresolv::LdResolver,
+    shebang::Shebang,
+};
+use std::{
+    collections::{HashSet, VecDeque},
+    fs,
+    path::{Path, PathBuf},
+};
+
+/// @INC directories in the order of the interpreter. "{t}" is a multiarch triplet,
+/// and each directory is also tried with the version subdirectories.
+const PERL_INC: &[&str] = &[
+    "/etc/perl",
+    "/usr/local/lib/{t}/perl",
+    "/usr/local/share/perl",
+    "/usr/lib/{t}/perl5",
+    "/usr/share/perl5",
+    "/usr/lib/{t}/perl-base",
+    "/usr/lib/{t}/perl",
+    "/usr/share/perl",
+    "/usr/local/lib/site_perl",
+    // RPM-based distributions
+    "/usr/local/lib64/perl5",
+    "/usr/local/share/perl5",
+    "/usr/lib64/perl5/vendor_perl",
+    "/usr/share/perl5/vendor_perl",
+    "/usr/lib64/perl5",
+    "/usr/lib/perl5",
+];
+
+/// Perl dependency: a module (Foo::Bar) or a file (foo.pl)
+enum PerlDep {
+    Module(String),
+    File(String),
+}
+
+pub struct PerlScanner<'a> {
+    inc: Vec<PathBuf>,
+    visited: HashSet<String>,
+    files: HashSet<PathBuf>,
+    elfscan: ElfScanner<'a>,
+}
+
+impl<'a> PerlScanner<'a> {
+    pub fn new(resolver: &'a LdResolver) -> Self {
+        PerlScanner {
+            inc: Vec::default(),
+            visited: HashSet::default(),
+            files: HashSet::default(),
+            elfscan: ElfScanner::new(resolver),
+        }
+    }
+
+    /// Returns true if the target is a Perl script
+    pub fn is_perl(target: &Path) -> bool {
+        Shebang::find_interp(target, "perl").is_some()
+    }
+
+    /// Get Perl versions (e.g. "5.36.0" and "5.36") from the versioned
+    /// interpreter binary next to the given one.
+    fn get_versions(interp: &Path) -> Vec<String> {
+        let interp = fs::canonicalize(interp).unwrap_or(interp.to_path_buf());
+        let mut names: Vec<String> = vec![interp.file_name().unwrap_or_default().to_str().unwrap_or_default().to_string()];
+        if let Ok(rd) = fs::read_dir(interp.parent().unwrap_or(Path::new("/usr/bin"))) {
+            names.extend(rd.filter_map(Result::ok).map(|e| e.file_name().to_str().unwrap_or_default().to_string()));
+        }
+
+        for n in names {
+            if let Some(v) =
+                n.strip_prefix("perl").filter(|v| v.starts_with("5.") && v.chars().all(|c| c.is_ascii_digit() || c == '.'))
+            {
+                let mut out = vec![v.to_string()];
+                if let Some((mm, _)) = v.rsplit_once('.').filter(|_| v.matches('.').count() > 1) {
+                    out.push(mm.to_string());
+                }
+                return out;
+            }
+        }
+
+        vec![]
+    }
+
+    /// Construct @INC, as the interpreter would do it
+    fn setup(&mut self, interp: &Path) {
+        let triplets: Vec<&str> = match ElfInfo::read_header(interp).and_then(|(m, is_64, be)| ElfArch::find(m, is_64, be)) {
+            Some(arch) => arch.get_triplets().to_vec(),
+            None => vec![],
+        };
+
+        let versions = Self::get_versions(interp);
+        let mut inc: Vec<PathBuf> = Vec::default();
+        for d in PERL_INC {
+            let bases: Vec<String> =
+                if d.contains("{t}") { triplets.iter().map(|t| d.replace("{t}", t)).collect() } else { vec![d.to_string()] };
+            for base in bases.iter().map(PathBuf::from) {
+                if versions.is_empty() {
+                    // Version is unknown, so all of them are taken
+                    if let Ok(rd) = fs::read_dir(&base) {
+                        inc.extend(
+                            rd.filter_map(Result::ok)
+                                .filter(|e| e.file_name().to_str().unwrap_or_default().starts_with("5."))
+                                .map(|e| e.path()),
+                        );
+                    }
+                } else {
+                    inc.extend(versions.iter().map(|v| base.join(v)));
+                }
+                inc.push(base);
+            }
+        }
+
+        // Version directories are often symlinks to each other
+        let mut seen: HashSet<PathBuf> = HashSet::default();
+        inc.retain(|d| d.is_dir() && seen.insert(fs::canonicalize(d).unwrap_or(d.to_owned())));
+        self.inc = inc;
+
+        log::debug!("Perl @INC: {:?}", self.inc);
+    }
+
+    /// Get arguments of a statement: quoted strings and qw() lists
+    fn get_args(s: &str) -> Vec<String> {
+        let mut out: Vec<String> = Vec::default();
+        let s = s.split_once(';').map(|(s, _)| s).unwrap_or(s);
+
+        let mut rest = s;
+        while let Some(i) = rest.find(['\'', '"', 'q']) {
+            let c = rest[i..].chars().next().unwrap_or_default();
+            if c == 'q' {
+                if let Some(qw) = rest[i..].strip_prefix("qw").map(|q| q.trim_start()) {
+                    if let Some(open) = qw.chars().next().filter(|c| !c.is_alphanumeric()) {
+                        let close = match open {
+                            '(' => ')',
+                            '[' => ']',
+                            '{' => '}',
+                            '<' => '>',
+                            c => c,
+                        };
+                        if let Some((words, tail)) = qw[open.len_utf8()..].split_once(close) {
+                            out.extend(words.split_whitespace().map(|w| w.to_string()));
+                            rest = tail;
+                            continue;
+                        }
+                    }
+                }
+                rest = &rest[i + 1..];
+                continue;
+            }
+
+            match rest[i + 1..].split_once(c) {
+                Some((arg, tail)) => {
+                    out.push(arg.to_string());
+                    rest = tail;
+                }
+                None => break,
+            }
+        }
+
+        out
+    }
+
+    /// Parse "use", "no" and "require" statements. Directories from "use lib" are added to @INC.
+    fn parse_deps(&mut self, script: &Path) -> Vec<PerlDep> {
+        let mut out: Vec<PerlDep> = Vec::default();
+        let bindir = script.parent().unwrap_or(Path::new("/")).to_str().unwrap_or_default().to_string();
+        let mut pod = false;
+
+        for l in fs::read_to_string(script).unwrap_or_default().lines() {
+            if l.starts_with("__END__") || l.starts_with("__DATA__") {
+                break;
+            }
+
+            // Skip documentation
+            if l.starts_with('=') && l.chars().nth(1).unwrap_or_default().is_alphabetic() {
+                pod = !l.starts_with("=cut");
+                continue;
+            }
+
+            let l = l.trim();
+            if pod || l.starts_with('#') {
+                continue;
+            }
+
+            for kw in ["use", "no", "require"] {
+                for (i, _) in l.match_indices(kw) {
+                    // Keyword should be a separate word and not a method or a hash key
+                    let prev = l[..i].chars().last().unwrap_or(' ');
+                    let rest = &l[i + kw.len()..];
+                    if prev.is_alphanumeric() || "_$@%>:{".contains(prev) || !rest.starts_with([' ', '\t']) {
+                        continue;
+                    }
+
+                    // "use" and "no" are statements on their own, "require" can be a part of an expression
+                    if kw != "require" && !l[..i].trim_end().is_empty() && !l[..i].trim_end().ends_with([';', '{', '}']) {
+                        continue;
+                    }
+
+                    let rest = rest.trim_start();
+                    let name = rest.chars().take_while(|c| c.is_alphanumeric() || *c == '_' || *c == ':').collect::<String>();
+                    let args = &rest[name.len()..];
+
+                    if name.is_empty() {
+                        if kw == "require" && rest.starts_with(['\'', '"']) {
+                            // Interpolated names cannot be resolved statically
+                            out.extend(Self::get_args(rest).into_iter().take(1).filter(|f| !f.contains('$')).map(PerlDep::File));
+                        }
+                        continue;
+                    }
+
+                    // Perl version, e.g. "use 5.010" or "use v5.36"
+                    if name.starts_with(|c: char| c.is_ascii_digit())
+                        || (name.starts_with('v') && name[1..].starts_with(|c: char| c.is_ascii_digit()))
+                    {
+                        continue;
+                    }
+
+                    match name.as_str() {
+                        "lib" => {
+                            for d in Self::get_args(args) {
+                                let d = PathBuf::from(d.replace("$FindBin::RealBin", &bindir).replace("$FindBin::Bin", &bindir));
+                                if d.is_absolute() && d.is_dir() && !self.inc.contains(&d) {
+                                    log::debug!("Adding {:?} to Perl @INC", d);
+                                    self.inc.insert(0, d);
+                                }
+                            }
+                            continue;
+                        }
+                        "parent" | "base" if !args.contains("-norequire") => {
+                            out.extend(Self::get_args(args).into_iter().map(PerlDep::Module));
+                        }
+                        _ => {}
+                    }
+
+                    out.push(PerlDep::Module(name.trim_end_matches(':').to_string()));
+                }
+            }
+        }
+
+        out
+    }
+
+    /// Find a file in @INC
+    fn locate(&self, rel: &str) -> Option<PathBuf> {
+        if Path::new(rel).is_absolute() {
+            return Some(PathBuf::from(rel)).filter(|p| p.is_file());
+        }
+
+        self.inc.iter().map(|d| d.join(rel)).find(|p| p.is_file())
+    }
+
+    /// Keep XS part of a module: "auto/Foo/Bar/Bar.so" and its autoloaded files.
+    /// Like DynaLoader, only the first one in @INC is taken.
+    fn keep_xs(&mut self, module: &str) {
+        let rel = format!("auto/{}", module.replace("::", "/"));
+        if let Some(auto) = self.inc.iter().map(|d| d.join(&rel)).find(|d| d.is_dir()) {
+            if let Ok(rd) = fs::read_dir(&auto) {
+                for p in rd.filter_map(Result::ok).map(|e| e.path()).filter(|p| p.is_file()) {
+                    if p.extension().unwrap_or_default() == "so" {
+                        log::debug!("Perl XS module {}: {:?}", module, p);
+                        self.files.extend(self.elfscan.scan(p.to_owned()).get_paths().to_owned());
+                    }
+                    self.files.insert(p);
+                }
+            }
+        }
+    }
+
+    /// Trace modules, starting from the script
+    fn trace(&mut self, script: &Path) {
+        let mut queue: VecDeque<PerlDep> = VecDeque::from(self.parse_deps(script));
+
+        while let Some(dep) = queue.pop_front() {
+            let (rel, module) = match dep {
+                PerlDep::Module(m) => (format!("{}.pm", m.replace("::", "/")), Some(m)),
+                PerlDep::File(f) => (f, None),
+            };
+
+            if !self.visited.insert(rel.to_owned()) {
+                continue;
+            }
+
+            let src = match self.locate(&rel) {
+                Some(src) => src,
+                None => {
+                    log::debug!("Perl module {} was not found", rel);
+                    continue;
+                }
+            };
+
+            self.files.insert(src.to_owned());
+            if let Some(m) = module {
+                self.keep_xs(&m);
+            }
+            queue.extend(self.parse_deps(&src));
+        }
+    }
+}
+
+impl Scanner for PerlScanner<'_> {
+    /// Scan Perl script for the used modules
+    fn scan(&mut self, pth: PathBuf) -> ScannerResult {
+        let interp = match Shebang::find_interp(&pth, "perl") {
+            Some(interp) => interp,
+            None => return ScannerResult::new(vec![]),
+        };
+
+        log::debug!("Scanning Perl modules in {}", pth.to_str().unwrap());
+        self.setup(&interp);
+        self.trace(&pth);
+
+        let mut files = self.files.iter().cloned().collect::<Vec<PathBuf>>();
+        files.sort();
+
+        ScannerResult::new(files)
+    }
+
+    /// Bogus trait implementation, does nothing in this case
+    fn exclude(&mut self, _: Vec<String>) -> &mut Self {
+        self
+    }
+
+    /// Dummy
+    fn contents(&mut self, _pkgname: String) -> Result<ScannerResult, std::io::Error> {
+        Err(std::io::Error::new(std::io::ErrorKind::Unsupported, ""))
+    }
+}
@@ -0,0 +1,487 @@
+/*
+Python module dependency tracer.
+
+Statically parses imports of a Python target and follows them
+through the sys.path layout of the interpreter inside the image.
+ */
+
+use super::{
+    binlib::ElfScanner,
+    general::{Scanner, ScannerResult},
+    ldresolv::LdResolver,
+    shebang::Shebang,
+};
+use std::{
+    collections::{HashSet, VecDeque},
+    fs,
+    path::{Path, PathBuf},
+};
+
+/// Modules, imported by the interpreter itself on startup
+const PY_STARTUP_MODULES: &[&str] = &[
+    "site",
+    "sitecustomize",
+    "usercustomize",
+    "codecs",
+    "io",
+    "abc",
+    "os",
+    "stat",
+    "posixpath",
+    "genericpath",
+    "_collections_abc",
+    "_sitebuiltins",
+];
+
+/// Packages, those are loading their modules dynamically, so they are kept entirely
+const PY_WHOLE_PACKAGES: &[&str] = &["encodings"];
+
+/// Prefixes of the Python installations
+const PY_PREFIXES: &[&str] = &["/usr/lib", "/usr/lib64", "/usr/local/lib"];
+
+pub struct PythonScanner<'a> {
+    version: String,
+    syspath: Vec<PathBuf>,
+    modules: HashSet<String>,
+    startup: Vec<String>,
+    toplevel: HashSet<String>,
+    files: HashSet<PathBuf>,
+    elfscan: ElfScanner<'a>,
+}
+
+/// Import statement: relative level, module and imported names
+struct PyImport {
+    level: usize,
+    module: String,
+    names: Vec<String>,
+}
+
+impl<'a> PythonScanner<'a> {
+    pub fn new(resolver: &'a LdResolver) -> Self {
+        PythonScanner {
+            version: String::default(),
+            syspath: Vec::default(),
+            modules: HashSet::default(),
+            startup: Vec::default(),
+            toplevel: HashSet::default(),
+            files: HashSet::default(),
+            elfscan: ElfScanner::new(resolver),
+        }
+    }
+
+    /// Returns true if the target is a Python script
+    pub fn is_python(target: &Path) -> bool {
+        Shebang::find_interp(target, "python").is_some()
+    }
+
+    /// Get Python version (e.g. "3.11") from the interpreter name,
+    /// or from the installed standard library, if the interpreter is just "python3".
+    fn get_version(interp: &Path) -> Option<String> {
+        let interp = fs::canonicalize(interp).unwrap_or(interp.to_path_buf());
+        let name = interp.file_name()?.to_str()?.strip_prefix("python")?.to_string();
+        if name.contains('.') {
+            return Some(name);
+        }
+
+        let mut versions: Vec<String> = Vec::default();
+        for pfx in PY_PREFIXES {
+            if let Ok(rd) = fs::read_dir(pfx) {
+                for e in rd.filter_map(Result::ok) {
+                    if let Some(v) = e.file_name().to_str().unwrap_or_default().strip_prefix(&format!("python{}.", name)) {
+                        if e.path().join("os.py").exists() {
+                            versions.push(format!("{}.{}", name, v));
+                        }
+                    }
+                }
+            }
+        }
+
+        // Sort by minor version, the latest wins
+        versions.sort_by_key(|v| v.split_once('.').map(|(_, m)| m.parse::<u32>().unwrap_or_default()).unwrap_or_default());
+        versions.pop()
+    }
+
+    /// Construct sys.path, as the interpreter would do it.
+    fn setup(&mut self, interp: &Path, target: &Path) -> bool {
+        self.version = match Self::get_version(interp) {
+            Some(v) => v,
+            None => {
+                log::warn!("Unable to detect Python version of {:?}", interp);
+                return false;
+            }
+        };
+
+        // Script directory is always the first
+        let mut syspath: Vec<PathBuf> = vec![target.parent().unwrap_or(Path::new("/")).to_path_buf()];
+        let mut sitedirs: Vec<PathBuf> = Vec::default();
+        for pfx in PY_PREFIXES {
+            let stdlib = PathBuf::from(format!("{}/python{}", pfx, self.version));
+            syspath.push(stdlib.to_owned());
+            syspath.push(stdlib.join("lib-dynload"));
+            sitedirs.push(stdlib.join("site-packages"));
+            sitedirs.push(stdlib.join("dist-packages"));
+        }
+
+        // Debian keeps the packages for all Python 3 versions together
+        sitedirs.push(PathBuf::from("/usr/lib/python3/dist-packages"));
+
+        for site in sitedirs.iter().filter(|d| d.is_dir()) {
+            syspath.push(site.to_owned());
+            self.load_pth(site, &mut syspath);
+        }
+
+        syspath.retain(|d| d.is_dir());
+        syspath.dedup();
+        self.syspath = syspath;
+
+        log::debug!("Python {} sys.path: {:?}", self.version, self.syspath);
+        true
+    }
+
+    /// Process .pth files of a site directory, which are adding paths or import modules at startup
+    fn load_pth(&mut self, site: &Path, syspath: &mut Vec<PathBuf>) {
+        let rd = match fs::read_dir(site) {
+            Ok(rd) => rd,
+            Err(_) => return,
+        };
+
+        for pth in rd.filter_map(Result::ok).map(|e| e.path()).filter(|p| p.extension().unwrap_or_default() == "pth") {
+            let data = fs::read_to_string(&pth).unwrap_or_default();
+            self.files.insert(pth);
+
+            for l in data.lines().map(|l| l.trim()).filter(|l| !l.is_empty() && !l.starts_with('#')) {
+                if l.starts_with("import ") || l.starts_with("import\t") {
+                    self.startup.extend(Self::parse_imports(l).into_iter().map(|imp| imp.module));
+                } else {
+                    syspath.push(site.join(l));
+                }
+            }
+        }
+    }
+
+    /// Join physical lines of import statements into logical ones
+    fn logical_lines(src: &str) -> Vec<String> {
+        let mut out: Vec<String> = Vec::default();
+        let mut lines = src.lines();
+
+        while let Some(l) = lines.next() {
+            let mut stmt = l.split_once('#').map(|(s, _)| s).unwrap_or(l).trim().to_string();
+            if !(stmt.starts_with("import ") || stmt.starts_with("from ")) {
+                // Dynamic imports with the literal module names
+                for call in ["import_module(", "__import__("] {
+                    for (i, _) in l.match_indices(call) {
+                        let arg = l[i + call.len()..].trim_start();
+                        if let Some(q) = arg.chars().next().filter(|c| *c == '"' || *c == '\'') {
+                            if let Some((m, _)) = arg[1..].split_once(q) {
+                                out.push(format!("import {}", m));
+                            }
+                        }
+                    }
+                }
+                continue;
+            }
+
+            while stmt.ends_with('\\') || stmt.matches('(').count() > stmt.matches(')').count() {
+                stmt = stmt.trim_end_matches('\\').to_string();
+                match lines.next() {
+                    Some(n) => stmt.push_str(&format!(" {}", n.split_once('#').map(|(s, _)| s).unwrap_or(n).trim())),
+                    None => break,
+                }
+            }
+
+            out.extend(stmt.split(';').map(|s| s.trim().to_string()));
+        }
+
+        out
+    }
+
+    /// Parse import statements
+    fn parse_imports(src: &str) -> Vec<PyImport> {
+        let mut out: Vec<PyImport> = Vec::default();
+        let names = |s: &str| -> Vec<String> {
+            s.replace(['(', ')'], " ")
+                .split(',')
+                .filter_map(|n| n.split_whitespace().next())
+                .filter(|n| *n != "*")
+                .map(|n| n.to_string())
+                .collect()
+        };
+
+        for stmt in Self::logical_lines(src) {
+            if let Some(imp) = stmt.strip_prefix("import ") {
+                for m in names(imp) {
+                    out.push(PyImport { level: 0, module: m, names: vec![] });
+                }
+            } else if let Some((m, imp)) = stmt.strip_prefix("from ").and_then(|s| s.split_once(" import ")) {
+                let m = m.trim();
+                let level = m.chars().take_while(|c| *c == '.').count();
+                out.push(PyImport { level, module: m[level..].to_string(), names: names(imp) });
+            }
+        }
+
+        out
+    }
+
+    /// Find a module file: package "__init__.py", module ".py", extension ".so",
+    /// or a directory of a namespace package.
+    fn locate(&self, module: &str) -> Option<PathBuf> {
+        let parts = module.split('.').collect::<Vec<&str>>();
+        for root in &self.syspath {
+            if let Some(p) = Self::locate_in(root, &parts) {
+                return Some(p);
+            }
+        }
+
+        None
+    }
+
+    fn locate_in(root: &Path, parts: &[&str]) -> Option<PathBuf> {
+        let mut dir = root.to_path_buf();
+        for (i, part) in parts.iter().enumerate() {
+            let pkg = dir.join(part);
+            let last = i == parts.len() - 1;
+
+            if pkg.join("__init__.py").is_file() {
+                if last {
+                    return Some(pkg.join("__init__.py"));
+                }
+                dir = pkg;
+                continue;
+            }
+
+            if !last {
+                // Namespace package
+                if pkg.is_dir() {
+                    dir = pkg;
+                    continue;
+                }
+                return None;
+            }
+
+            let py = dir.join(format!("{}.py", part));
+            if py.is_file() {
+                return Some(py);
+            }
+
+            // Extension module, e.g. "_ssl.cpython-311-x86_64-linux-gnu.so" or "_foo.abi3.so"
+            if let Ok(rd) = fs::read_dir(&dir) {
+                for e in rd.filter_map(Result::ok) {
+                    let fname = e.file_name().to_str().unwrap_or_default().to_string();
+                    if fname.ends_with(".so") && fname.split('.').next() == Some(part) {
+                        return Some(e.path());
+                    }
+                }
+            }
+
+            if pkg.is_dir() {
+                return Some(pkg);
+            }
+        }
+
+        None
+    }
+
+    /// Keep a source file and its bytecode cache
+    fn keep_source(&mut self, src: &Path) {
+        self.files.insert(src.to_path_buf());
+
+        let (dir, stem) = match (src.parent(), src.file_stem()) {
+            (Some(dir), Some(stem)) => (dir, stem.to_str().unwrap_or_default()),
+            _ => return,
+        };
+
+        if let Ok(rd) = fs::read_dir(dir.join("__pycache__")) {
+            for e in rd.filter_map(Result::ok) {
+                if e.file_name().to_str().unwrap_or_default().starts_with(&format!("{}.", stem)) {
+                    self.files.insert(e.path());
+                }
+            }
+        }
+    }
+
+    /// Keep data files of the package, which are not code
+    fn keep_package_data(&mut self, pkgdir: &Path) {
+        if let Ok(rd) = fs::read_dir(pkgdir) {
+            for p in rd.filter_map(Result::ok).map(|e| e.path()).filter(|p| p.is_file()) {
+                let ext = p.extension().unwrap_or_default().to_str().unwrap_or_default();
+                if !["py", "pyc", "so"].contains(&ext) {
+                    self.files.insert(p);
+                }
+            }
+        }
+    }
+
+    /// Keep the whole directory
+    fn keep_tree(&mut self, dir: &Path) {
+        for p in walkdir::WalkDir::new(dir).into_iter().filter_map(Result::ok).map(|e| e.into_path()) {
+            if p.is_file() {
+                if p.extension().unwrap_or_default() == "so" {
+                    self.files.extend(self.elfscan.scan(p.to_owned()).get_paths().to_owned());
+                }
+                self.files.insert(p);
+            }
+        }
+    }
+
+    /// Keep distribution metadata (*.dist-info, *.egg-info) of the kept top-level packages,
+    /// as some packages are looking for their entry points or versions at runtime.
+    fn keep_metadata(&mut self) {
+        for site in self.syspath.clone() {
+            let rd = match fs::read_dir(&site) {
+                Ok(rd) => rd,
+                Err(_) => continue,
+            };
+
+            for meta in rd.filter_map(Result::ok).map(|e| e.path()).filter(|p| {
+                let n = p.to_str().unwrap_or_default();
+                p.is_dir() && (n.ends_with(".dist-info") || n.ends_with(".egg-info"))
+            }) {
+                let mut tops = fs::read_to_string(meta.join("top_level.txt"))
+                    .unwrap_or_default()
+                    .lines()
+                    .map(|l| l.trim().to_string())
+                    .collect::<Vec<String>>();
+
+                for l in fs::read_to_string(meta.join("RECORD")).unwrap_or_default().lines() {
+                    if let Some(top) = l.split([',', '/']).next() {
+                        tops.push(top.trim_end_matches(".py").to_string());
+                    }
+                }
+
+                if tops.iter().any(|t| self.toplevel.contains(t)) {
+                    self.keep_tree(&meta);
+                }
+            }
+        }
+    }
+
+    /// Resolve absolute module name of a relative import
+    fn absolute(module: &str, is_pkg: bool, imp: &PyImport) -> Option<String> {
+        if imp.level == 0 {
+            return Some(imp.module.to_owned());
+        }
+
+        let mut base = module.split('.').collect::<Vec<&str>>();
+        if !is_pkg {
+            base.pop();
+        }
+
+        for _ in 1..imp.level {
+            base.pop()?;
+        }
+
+        if !imp.module.is_empty() {
+            base.push(&imp.module);
+        }
+
+        if base.is_empty() {
+            None
+        } else {
+            Some(base.join("."))
+        }
+    }
+
+    /// Follow imports of the source, adding found modules to the queue
+    fn follow(&self, module: &str, src: &Path, queue: &mut VecDeque<String>) {
+        let is_pkg = src.file_name().unwrap_or_default() == "__init__.py";
+        for imp in Self::parse_imports(&fs::read_to_string(src).unwrap_or_default()) {
+            if let Some(m) = Self::absolute(module, is_pkg, &imp) {
+                // "import a.b.c" imports also "a" and "a.b"
+                let parts = m.split('.').collect::<Vec<&str>>();
+                for i in 1..=parts.len() {
+                    queue.push_back(parts[..i].join("."));
+                }
+
+                // "from a import b" where "b" might be a submodule
+                for n in &imp.names {
+                    queue.push_back(format!("?{}.{}", m, n));
+                }
+            }
+        }
+    }
+
+    /// Trace modules, starting from the script
+    fn trace(&mut self, target: &Path) {
+        let mut queue: VecDeque<String> = VecDeque::default();
+        queue.extend(PY_STARTUP_MODULES.iter().chain(PY_WHOLE_PACKAGES).map(|m| m.to_string()));
+        queue.extend(self.startup.drain(..));
+        self.follow("__main__", target, &mut queue);
+
+        while let Some(m) = queue.pop_front() {
+            // Optional modules are silently skipped, if not found
+            let (m, optional) = match m.strip_prefix('?') {
+                Some(m) => (m.to_string(), true),
+                None => (m, false),
+            };
+
+            if !self.modules.insert(m.to_owned()) {
+                continue;
+            }
+
+            let src = match self.locate(&m) {
+                Some(src) => src,
+                None => {
+                    if !optional {
+                        log::debug!("Python module {} was not found (built-in?)", m);
+                    }
+                    continue;
+                }
+            };
+
+            self.toplevel.insert(m.split('.').next().unwrap_or_default().to_string());
+            if PY_WHOLE_PACKAGES.contains(&m.as_str()) {
+                self.keep_tree(src.parent().unwrap_or(Path::new("/")));
+                continue;
+            }
+
+            if src.is_dir() {
+                continue;
+            }
+
+            if src.extension().unwrap_or_default() == "so" {
+                log::debug!("Python extension module {}: {:?}", m, src);
+                self.files.insert(src.to_owned());
+                self.files.extend(self.elfscan.scan(src).get_paths().to_owned());
+                continue;
+            }
+
+            self.keep_source(&src);
+            if src.file_name().unwrap_or_default() == "__init__.py" {
+                self.keep_package_data(src.parent().unwrap_or(Path::new("/")));
+            }
+            self.follow(&m, &src, &mut queue);
+        }
+
+        self.keep_metadata();
+    }
+}
+
+impl Scanner for PythonScanner<'_> {
+    /// Scan Python script for the imported modules
+    fn scan(&mut self, pth: PathBuf) -> ScannerResult {
+        let interp = match Shebang::find_interp(&pth, "python") {
+            Some(interp) => interp,
+            None => return ScannerResult::new(vec![]),
+        };
+
+        log::debug!("Scanning Python imports in {}", pth.to_str().unwrap());
+        if self.setup(&interp, &pth) {
+            self.trace(&pth);
+        }
+
+        let mut files = self.files.iter().cloned().collect::<Vec<PathBuf>>();
+        files.sort();
+
+        ScannerResult::new(files)
+    }
+
+    /// Bogus trait implementation, does nothing in this case
+    fn exclude(&mut self, _: Vec<String>) -> &mut Self {
+        self
+    }
+
+    /// Dummy
+    fn contents(&mut self, _pkgname: String) -> Result<ScannerResult, std::io::Error> {
+        Err(std::io::Error::new(std::io::ErrorKind::Unsupported, ""))
+    }
+}
@@ -0,0 +1,468 @@
+/*
+RPM database reader.
+
+Reads package headers directly from the rpmdb of the image,
+so no rpm binary is needed. Supported are sqlite (rpmdb.sqlite),
+NDB (Packages.db) and the legacy Berkeley DB hash (Packages).
+ */
+
+use super::pkgdb::{DbPackage, PackageDb};
+use rusqlite::{Connection, OpenFlags};
+use std::{
+    collections::HashMap,
+    fs,
+    io::{Error, ErrorKind},
+    path::{Path, PathBuf},
+};
+
+/// Locations of the rpmdb: newer distributions are using /usr/lib/sysimage
+const RPMDB_DIRS: &[&str] = &["/usr/lib/sysimage/rpm", "/var/lib/rpm"];
+
+// Header tags
+const TAG_NAME: u32 = 1000;
+const TAG_OLDFILENAMES: u32 = 1027;
+const TAG_PROVIDENAME: u32 = 1047;
+const TAG_REQUIREFLAGS: u32 = 1048;
+const TAG_REQUIRENAME: u32 = 1049;
+const TAG_DIRINDEXES: u32 = 1116;
+const TAG_BASENAMES: u32 = 1117;
+const TAG_DIRNAMES: u32 = 1118;
+const TAG_RECOMMENDNAME: u32 = 5046;
+const TAG_SUGGESTNAME: u32 = 5049;
+
+// Header data types
+const TYPE_INT32: u32 = 4;
+const TYPE_STRING: u32 = 6;
+const TYPE_STRING_ARRAY: u32 = 8;
+const TYPE_I18NSTRING: u32 = 9;
+
+/// Requirement is satisfied by rpm itself, e.g. "rpmlib(PayloadIsZstd)"
+const RPMSENSE_RPMLIB: u32 = 1 << 24;
+
+// Berkeley DB hash
+const BDB_HASH_MAGIC: u32 = 0x061561;
+const BDB_PAGE_HASH_UNSORTED: u8 = 2;
+const BDB_PAGE_HASH: u8 = 13;
+const BDB_ITEM_KEYDATA: u8 = 1;
+const BDB_ITEM_OFFPAGE: u8 = 3;
+const BDB_PAGE_HEADER: usize = 26;
+
+// NDB
+const NDB_MAGIC: &[u8] = b"RpmP";
+const NDB_SLOT_MAGIC: &[u8] = b"Slot";
+const NDB_BLOB_MAGIC: &[u8] = b"BlbS";
+const NDB_HEADER_SIZE: usize = 32;
+const NDB_PAGE_SIZE: usize = 4096;
+const NDB_BLK_SIZE: usize = 16;
+
+/// Installed package, as recorded in the rpmdb
+#[derive(Clone, Default)]
+pub struct RpmHeader {
+    name: String,
+    files: Vec<PathBuf>,
+    requires: Vec<String>,
+    recommends: Vec<String>,
+    suggests: Vec<String>,
+    provides: Vec<String>,
+}
+
+impl RpmHeader {
+    /// Parse header blob: index entries, followed by the data store
+    pub fn parse(blob: &[u8]) -> Option<Self> {
+        let il = u32::from_be_bytes(blob.get(0..4)?.try_into().ok()?) as usize;
+        let dl = u32::from_be_bytes(blob.get(4..8)?.try_into().ok()?) as usize;
+        let data = blob.get(8 + il * 16..8 + il * 16 + dl)?;
+
+        let mut strings: HashMap<u32, Vec<String>> = HashMap::default();
+        let mut ints: HashMap<u32, Vec<u32>> = HashMap::default();
+        for e in blob.get(8..8 + il * 16)?.chunks_exact(16) {
+            let field = |i: usize| u32::from_be_bytes(e[i..i + 4].try_into().unwrap());
+            let (tag, tp, offset, count) = (field(0), field(4), field(8) as usize, field(12) as usize);
+            match tp {
+                TYPE_STRING | TYPE_STRING_ARRAY | TYPE_I18NSTRING => {
+                    let count = if tp == TYPE_STRING { 1 } else { count };
+                    strings.insert(
+                        tag,
+                        data.get(offset..)?
+                            .split(|b| *b == 0)
+                            .take(count)
+                            .map(|s| String::from_utf8_lossy(s).to_string())
+                            .collect(),
+                    );
+                }
+                TYPE_INT32 => {
+                    ints.insert(
+                        tag,
+                        data.get(offset..offset + count * 4)?
+                            .chunks_exact(4)
+                            .map(|b| u32::from_be_bytes(b.try_into().unwrap()))
+                            .collect(),
+                    );
+                }
+                _ => {}
+            }
+        }
+
+        let mut take = |tag: u32| strings.remove(&tag).unwrap_or_default();
+        let one = |v: Vec<String>| v.into_iter().next().unwrap_or_default();
+
+        let mut hdr = RpmHeader {
+            name: one(take(TAG_NAME)),
+            provides: take(TAG_PROVIDENAME),
+            recommends: take(TAG_RECOMMENDNAME),
+            suggests: take(TAG_SUGGESTNAME),
+            ..Default::default()
+        };
+
+        if hdr.name.is_empty() {
+            return None;
+        }
+
+        // Files are stored either as basenames with the directory indexes, or as full paths by old rpm
+        let (basenames, dirnames) = (take(TAG_BASENAMES), take(TAG_DIRNAMES));
+        let dirindexes = ints.remove(&TAG_DIRINDEXES).unwrap_or_default();
+        hdr.files = basenames
+            .iter()
+            .zip(dirindexes.iter())
+            .filter_map(|(b, i)| dirnames.get(*i as usize).map(|d| PathBuf::from(format!("{}{}", d, b))))
+            .collect();
+        hdr.files.extend(take(TAG_OLDFILENAMES).into_iter().map(PathBuf::from));
+
+        let reqflags = ints.remove(&TAG_REQUIREFLAGS).unwrap_or_default();
+        hdr.requires = take(TAG_REQUIRENAME)
+            .into_iter()
+            .enumerate()
+            .filter(|(i, r)| reqflags.get(*i).unwrap_or(&0) & RPMSENSE_RPMLIB == 0 && !r.starts_with("rpmlib("))
+            .map(|(_, r)| r)
+            .collect();
+
+        Some(hdr)
+    }
+}
+
+impl DbPackage for RpmHeader {
+    fn get_name(&self) -> &str {
+        &self.name
+    }
+
+    /// Files and directories of the package
+    fn get_files(&self) -> &[PathBuf] {
+        &self.files
+    }
+
+    fn get_provides(&self) -> &[String] {
+        &self.provides
+    }
+
+    /// Requires are the hard dependencies, Recommends and Suggests are the weak ones.
+    /// Rich dependencies, like "(foo if bar)", are not resolved.
+    fn get_deps(&self) -> Vec<(&'static str, &'static str, &[String])> {
+        vec![
+            ("Requires", "depends", &self.requires),
+            ("Recommends", "recommends", &self.recommends),
+            ("Suggests", "suggests", &self.suggests),
+        ]
+    }
+}
+
+pub struct RpmDb {
+    packages: Vec<RpmHeader>,
+}
+
+impl RpmDb {
+    /// Find the rpmdb file in the image: sqlite, NDB or Berkeley DB
+    pub fn find() -> Option<PathBuf> {
+        RPMDB_DIRS
+            .iter()
+            .flat_map(|d| ["rpmdb.sqlite", "Packages.db", "Packages"].iter().map(move |f| Path::new(d).join(f)))
+            .find(|p| p.is_file())
+    }
+
+    /// Read headers from sqlite database. The image is never modified.
+    fn read_sqlite(db: &Path) -> Result<Vec<Vec<u8>>, Error> {
+        let err = |e: rusqlite::Error| Error::new(ErrorKind::InvalidData, e.to_string());
+        let conn = Connection::open_with_flags(
+            format!("file:{}?immutable=1", db.to_str().unwrap_or_default()),
+            OpenFlags::SQLITE_OPEN_READ_ONLY | OpenFlags::SQLITE_OPEN_URI,
+        )
+        .map_err(err)?;
+
+        let mut stmt = conn.prepare("SELECT blob FROM Packages").map_err(err)?;
+        let rows = stmt.query_map([], |r| r.get::<_, Vec<u8>>(0)).map_err(err)?;
+
+        rows.collect::<Result<Vec<Vec<u8>>, rusqlite::Error>>().map_err(err)
+    }
+
+    /// Read headers from NDB: slot pages point to the blobs with the headers
+    fn read_ndb(data: &[u8]) -> Result<Vec<Vec<u8>>, Error> {
+        let u32_at = |o: usize| data.get(o..o + 4).map(|b| u32::from_le_bytes(b.try_into().unwrap()) as usize);
+        if data.get(0..4) != Some(NDB_MAGIC) {
+            return Err(Error::new(ErrorKind::InvalidData, "Not an NDB RPM database"));
+        }
+
+        let mut out: Vec<Vec<u8>> = Vec::default();
+        let slots_end = u32_at(12).unwrap_or_default() * NDB_PAGE_SIZE;
+        for slot in (NDB_HEADER_SIZE..slots_end).step_by(16) {
+            if data.get(slot..slot + 4) != Some(NDB_SLOT_MAGIC) || u32_at(slot + 4).unwrap_or_default() == 0 {
+                continue;
+            }
+
+            let blk = u32_at(slot + 8).unwrap_or_default() * NDB_BLK_SIZE;
+            if data.get(blk..blk + 4) != Some(NDB_BLOB_MAGIC) {
+                log::warn!("Broken NDB blob at offset {}", blk);
+                continue;
+            }
+
+            let len = u32_at(blk + 12).unwrap_or_default();
+            if let Some(blob) = data.get(blk + 16..blk + 16 + len) {
+                out.push(blob.to_vec());
+            }
+        }
+
+        Ok(out)
+    }
+
+    /// Read headers from Berkeley DB hash database, following the overflow pages
+    fn read_bdb(data: &[u8]) -> Result<Vec<Vec<u8>>, Error> {
+        let magic = data.get(12..16).ok_or(Error::new(ErrorKind::InvalidData, "Truncated Berkeley DB"))?;
+        let be = match magic {
+            m if u32::from_le_bytes(m.try_into().unwrap()) == BDB_HASH_MAGIC => false,
+            m if u32::from_be_bytes(m.try_into().unwrap()) == BDB_HASH_MAGIC => true,
+            _ => return Err(Error::new(ErrorKind::InvalidData, "Not a Berkeley DB hash database")),
+        };
+
+        let u16_at = |o: usize| {
+            data.get(o..o + 2)
+                .map(|b| if be { u16::from_be_bytes([b[0], b[1]]) } else { u16::from_le_bytes([b[0], b[1]]) } as usize)
+        };
+        let u32_at = |o: usize| {
+            data.get(o..o + 4).map(|b| {
+                let b: [u8; 4] = b.try_into().unwrap();
+                (if be { u32::from_be_bytes(b) } else { u32::from_le_bytes(b) }) as usize
+            })
+        };
+
+        let pagesize = u32_at(20).filter(|s| *s >= 512).ok_or(Error::new(ErrorKind::InvalidData, "Bad Berkeley DB page size"))?;
+
+        // Collect data of overflow pages chain
+        let overflow = |mut pgno: usize, len: usize| -> Vec<u8> {
+            let mut out: Vec<u8> = Vec::default();
+            while pgno != 0 && out.len() < len {
+                let pg = pgno * pagesize;
+                let chunk = u16_at(pg + 22).unwrap_or_default();
+                match data.get(pg + BDB_PAGE_HEADER..pg + BDB_PAGE_HEADER + chunk) {
+                    Some(d) => out.extend(d),
+                    None => break,
+                }
+                pgno = u32_at(pg + 16).unwrap_or_default();
+            }
+            out
+        };
+
+        let mut out: Vec<Vec<u8>> = Vec::default();
+        for pg in (pagesize..data.len()).step_by(pagesize) {
+            if !matches!(data.get(pg + 25), Some(&BDB_PAGE_HASH) | Some(&BDB_PAGE_HASH_UNSORTED)) {
+                continue;
+            }
+
+            // Items are key/data pairs, only the data is needed
+            let entries = u16_at(pg + 20).unwrap_or_default();
+            for i in (1..entries).step_by(2) {
+                let off = u16_at(pg + BDB_PAGE_HEADER + i * 2).unwrap_or_default();
+                let end = u16_at(pg + BDB_PAGE_HEADER + (i - 1) * 2).unwrap_or(pagesize);
+                let item = pg + off;
+                match data.get(item) {
+                    Some(&BDB_ITEM_OFFPAGE) => {
+                        let len = u32_at(item + 8).unwrap_or_default();
+                        out.push(overflow(u32_at(item + 4).unwrap_or_default(), len));
+                    }
+                    Some(&BDB_ITEM_KEYDATA) if end > off => {
+                        if let Some(d) = data.get(item + 1..pg + end) {
+                            out.push(d.to_vec());
+                        }
+                    }
+                    _ => {}
+                }
+            }
+        }
+
+        Ok(out)
+    }
+}
+
+impl PackageDb for RpmDb {
+    type Package = RpmHeader;
+    const NAME: &'static str = "rpm";
+
+    /// Read all the package headers
+    fn read() -> Result<Self, Error> {
+        let db = Self::find().ok_or(Error::new(ErrorKind::NotFound, "Unable to find the RPM database"))?;
+        log::debug!("Reading RPM database at {:?}", db);
+
+        let blobs = match db.file_name().unwrap_or_default().to_str().unwrap_or_default() {
+            "rpmdb.sqlite" => Self::read_sqlite(&db)?,
+            "Packages.db" => Self::read_ndb(&fs::read(&db)?)?,
+            _ => Self::read_bdb(&fs::read(&db)?)?,
+        };
+
+        let packages = blobs.iter().filter_map(|b| RpmHeader::parse(b)).collect::<Vec<RpmHeader>>();
+        log::debug!("Found {} RPM packages", packages.len());
+
+        Ok(RpmDb { packages })
+    }
+
+    fn get_db_paths() -> Vec<PathBuf> {
+        Self::find().and_then(|p| p.parent().map(|p| p.to_path_buf())).into_iter().collect()
+    }
+
+    fn get_packages(&self) -> &[RpmHeader] {
+        &self.packages
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Build a header blob from the tags: type and the values
+    fn header(tags: &[(u32, u32, &[&str])], ints: &[(u32, &[u32])]) -> Vec<u8> {
+        let (mut index, mut store): (Vec<u8>, Vec<u8>) = (Vec::default(), Vec::default());
+        for (tag, tp, values) in tags {
+            for v in [*tag, *tp, store.len() as u32, values.len() as u32] {
+                index.extend(v.to_be_bytes());
+            }
+            for s in *values {
+                store.extend(s.as_bytes());
+                store.push(0);
+            }
+        }
+        for (tag, values) in ints {
+            for v in [*tag, TYPE_INT32, store.len() as u32, values.len() as u32] {
+                index.extend(v.to_be_bytes());
+            }
+            store.extend(values.iter().flat_map(|v| v.to_be_bytes()));
+        }
+
+        let mut blob = ((tags.len() + ints.len()) as u32).to_be_bytes().to_vec();
+        blob.extend((store.len() as u32).to_be_bytes());
+        blob.extend(index);
+        blob.extend(store);
+        blob
+    }
+
+    fn put(data: &mut [u8], offset: usize, bytes: &[u8]) {
+        data[offset..offset + bytes.len()].copy_from_slice(bytes);
+    }
+
+    #[test]
+    fn test_parse_header() {
+        let blob = header(
+            &[
+                (TAG_NAME, TYPE_STRING, &["bash"]),
+                (TAG_BASENAMES, TYPE_STRING_ARRAY, &["bash", "bashrc"]),
+                (TAG_DIRNAMES, TYPE_STRING_ARRAY, &["/usr/bin/", "/etc/"]),
+                (TAG_OLDFILENAMES, TYPE_STRING_ARRAY, &["/bin/sh"]),
+                (TAG_REQUIRENAME, TYPE_STRING_ARRAY, &["glibc", "rpmlib(CompressedFileNames)", "ncurses-libs"]),
+                (TAG_PROVIDENAME, TYPE_STRING_ARRAY, &["bash", "/bin/sh"]),
+                (TAG_RECOMMENDNAME, TYPE_STRING_ARRAY, &["bash-completion"]),
+            ],
+            &[(TAG_DIRINDEXES, &[0, 1]), (TAG_REQUIREFLAGS, &[0, RPMSENSE_RPMLIB, 0])],
+        );
+
+        let hdr = RpmHeader::parse(&blob).unwrap();
+        assert_eq!(hdr.get_name(), "bash");
+        assert_eq!(hdr.get_files(), &[PathBuf::from("/usr/bin/bash"), PathBuf::from("/etc/bashrc"), PathBuf::from("/bin/sh")]);
+        assert_eq!(hdr.requires, vec!["glibc", "ncurses-libs"]);
+        assert_eq!(hdr.get_provides(), &["bash", "/bin/sh"]);
+        assert_eq!(hdr.recommends, vec!["bash-completion"]);
+        assert!(hdr.suggests.is_empty());
+    }
+
+    #[test]
+    fn test_parse_header_invalid() {
+        // No name, truncated and bogus data store length
+        assert!(RpmHeader::parse(&header(&[(TAG_PROVIDENAME, TYPE_STRING_ARRAY, &["foo"])], &[])).is_none());
+
+        let blob = header(&[(TAG_NAME, TYPE_STRING, &["bash"])], &[]);
+        assert!(RpmHeader::parse(&blob[..blob.len() - 1]).is_none());
+        assert!(RpmHeader::parse(&[0xff; 8]).is_none());
+    }
+
+    #[test]
+    fn test_read_ndb() {
+        let blob = header(&[(TAG_NAME, TYPE_STRING, &["bash"])], &[]);
+        let mut data = vec![0u8; NDB_PAGE_SIZE * 2];
+        put(&mut data, 0, NDB_MAGIC);
+        put(&mut data, 12, &1u32.to_le_bytes());
+
+        // Used slot, pointing to the blob in the second page, and a free slot
+        put(&mut data, 32, NDB_SLOT_MAGIC);
+        put(&mut data, 36, &1u32.to_le_bytes());
+        put(&mut data, 40, &((NDB_PAGE_SIZE / NDB_BLK_SIZE) as u32).to_le_bytes());
+        put(&mut data, 48, NDB_SLOT_MAGIC);
+
+        put(&mut data, NDB_PAGE_SIZE, NDB_BLOB_MAGIC);
+        put(&mut data, NDB_PAGE_SIZE + 12, &(blob.len() as u32).to_le_bytes());
+        put(&mut data, NDB_PAGE_SIZE + 16, &blob);
+
+        assert_eq!(RpmDb::read_ndb(&data).unwrap(), vec![blob]);
+        assert!(RpmDb::read_ndb(b"not an NDB").is_err());
+    }
+
+    /// Berkeley DB hash with two entries: one on the page and one on two overflow pages
+    fn bdb(be: bool, inline: &[u8], offpage: &[u8]) -> Vec<u8> {
+        let pagesize = 512;
+        let u16b = |v: usize| if be { (v as u16).to_be_bytes() } else { (v as u16).to_le_bytes() };
+        let u32b = |v: usize| if be { (v as u32).to_be_bytes() } else { (v as u32).to_le_bytes() };
+
+        let mut data = vec![0u8; pagesize * 4];
+        put(&mut data, 12, &u32b(BDB_HASH_MAGIC as usize));
+        put(&mut data, 20, &u32b(pagesize));
+
+        // Items are placed from the end of the page, data item ends where its key starts
+        let pg = pagesize;
+        let key0 = pagesize - 5;
+        let data0 = key0 - 1 - inline.len();
+        let key1 = data0 - 5;
+        let data1 = key1 - 12;
+        put(&mut data, pg + key0, &[BDB_ITEM_KEYDATA, 1, 0, 0, 0]);
+        put(&mut data, pg + data0, &[BDB_ITEM_KEYDATA]);
+        put(&mut data, pg + data0 + 1, inline);
+        put(&mut data, pg + key1, &[BDB_ITEM_KEYDATA, 2, 0, 0, 0]);
+        put(&mut data, pg + data1, &[BDB_ITEM_OFFPAGE]);
+        put(&mut data, pg + data1 + 4, &u32b(2));
+        put(&mut data, pg + data1 + 8, &u32b(offpage.len()));
+        put(&mut data, pg + 20, &u16b(4));
+        put(&mut data, pg + 25, &[BDB_PAGE_HASH]);
+        for (i, off) in [key0, data0, key1, data1].iter().enumerate() {
+            put(&mut data, pg + BDB_PAGE_HEADER + i * 2, &u16b(*off));
+        }
+
+        // Overflow chain: page 2 -> page 3
+        let (head, tail) = offpage.split_at(offpage.len() / 2);
+        for (pgno, chunk, next) in [(2, head, 3), (3, tail, 0)] {
+            let pg = pgno * pagesize;
+            put(&mut data, pg + 16, &u32b(next));
+            put(&mut data, pg + 22, &u16b(chunk.len()));
+            put(&mut data, pg + 25, &[7]);
+            put(&mut data, pg + BDB_PAGE_HEADER, chunk);
+        }
+
+        data
+    }
+
+    #[test]
+    fn test_read_bdb() {
+        let bash = header(&[(TAG_NAME, TYPE_STRING, &["bash"])], &[]);
+        let glibc = header(&[(TAG_NAME, TYPE_STRING, &["glibc"]), (TAG_PROVIDENAME, TYPE_STRING_ARRAY, &["libc.so.6"])], &[]);
+
+        for be in [false, true] {
+            let blobs = RpmDb::read_bdb(&bdb(be, &bash, &glibc)).unwrap();
+            assert_eq!(blobs, vec![bash.to_owned(), glibc.to_owned()]);
+            let names = blobs.iter().filter_map(|b| RpmHeader::parse(b)).map(|h| h.name).collect::<Vec<String>>();
+            assert_eq!(names, vec!["bash", "glibc"]);
+        }
+
+        assert!(RpmDb::read_bdb(&[0u8; 64]).is_err());
+    }
+}
@@ -0,0 +1,484 @@
+/*
+Shell script command tracer.
+
+Best-effort parser of POSIX shell scripts, which finds external
+commands those are called by the script and resolves them through $PATH.
+ */
+
+use super::{
+    general::{Scanner, ScannerResult},
+    shebang::{which, Shebang},
+};
+use std::{
+    collections::HashSet,
+    fs,
+    iter::Peekable,
+    path::{Path, PathBuf},
+    str::Chars,
+};
+
+/// Shell interpreters
+const SH_INTERPRETERS: &[&str] = &["sh", "bash", "dash", "ash", "ksh", "mksh", "zsh"];
+
+/// Builtins and reserved words, those are not external commands
+const SH_BUILTINS: &[&str] = &[
+    ":", ".", "[", "[[", "]]", "alias", "bg", "break", "builtin", "cd", "command", "continue", "declare", "echo", "eval", "exec",
+    "exit", "export", "false", "fg", "getopts", "hash", "jobs", "kill", "let", "local", "printf", "pwd", "read", "readonly",
+    "return", "set", "shift", "shopt", "source", "test", "times", "trap", "true", "type", "typeset", "ulimit", "umask",
+    "unalias", "unset", "wait",
+];
+
+/// Reserved words, after which a command follows
+const SH_RESERVED: &[&str] = &["if", "then", "else", "elif", "fi", "do", "done", "while", "until", "!", "{", "}", "time"];
+
+/// Commands, those are running another command
+const SH_WRAPPERS: &[&str] = &["exec", "command", "env", "nohup", "nice", "timeout", "xargs", "sudo", "chroot", "stdbuf"];
+
+/// Lexical token of a shell script
+#[derive(Debug, PartialEq)]
+enum ShToken {
+    Word(String),
+    Op(String),
+    Redir(bool), // true if a target word follows
+}
+
+pub struct ShellScanner {
+    commands: Vec<PathBuf>,
+    sources: Vec<PathBuf>,
+    functions: HashSet<String>,
+}
+
+impl ShellScanner {
+    pub fn new() -> Self {
+        ShellScanner { commands: Vec::default(), sources: Vec::default(), functions: HashSet::default() }
+    }
+
+    /// Returns true if the target is a shell script
+    pub fn is_shell(target: &Path) -> bool {
+        Shebang::chain(target)
+            .iter()
+            .any(|i| SH_INTERPRETERS.contains(&i.file_name().unwrap_or_default().to_str().unwrap_or_default()))
+    }
+
+    /// Read until the closing character, keeping the content
+    fn read_until(chars: &mut Peekable<Chars>, end: char, buf: &mut String) {
+        while let Some(c) = chars.next() {
+            if c == end {
+                return;
+            }
+            if c == '\\' && end != '\'' {
+                if let Some(n) = chars.next() {
+                    buf.push(n);
+                }
+                continue;
+            }
+            buf.push(c);
+        }
+    }
+
+    /// Skip arithmetic expression "((...))"
+    fn skip_arithmetic(chars: &mut Peekable<Chars>) {
+        let mut depth = 2;
+        for c in chars.by_ref() {
+            match c {
+                '(' => depth += 1,
+                ')' => depth -= 1,
+                _ => {}
+            }
+            if depth == 0 {
+                return;
+            }
+        }
+    }
+
+    /// Finish the current word
+    fn flush(word: &mut String, out: &mut Vec<ShToken>) {
+        if !word.is_empty() {
+            out.push(ShToken::Word(std::mem::take(word)));
+        }
+    }
+
+    /// Split the script into words and operators. Command substitutions
+    /// are lexed in place, surrounded by "$(" and "$)" operators.
+    fn lex(chars: &mut Peekable<Chars>, end: Option<char>, out: &mut Vec<ShToken>) {
+        let mut word = String::default();
+        let mut heredocs: Vec<String> = Vec::default();
+
+        while let Some(c) = chars.next() {
+            if Some(c) == end {
+                break;
+            }
+
+            match c {
+                '\\' => match chars.next() {
+                    Some('\n') | None => {}
+                    Some(n) => word.push(n),
+                },
+                '\'' => Self::read_until(chars, '\'', &mut word),
+                '"' => {
+                    // Command substitutions inside the double quotes
+                    while let Some(c) = chars.next() {
+                        match c {
+                            '"' => break,
+                            '\\' => {
+                                chars.next();
+                            }
+                            '$' if chars.peek() == Some(&'(') => {
+                                chars.next();
+                                word.push('$');
+                                if chars.peek() == Some(&'(') {
+                                    chars.next();
+                                    Self::skip_arithmetic(chars);
+                                } else {
+                                    out.push(ShToken::Op("$(".to_string()));
+                                    Self::lex(chars, Some(')'), out);
+                                    out.push(ShToken::Op("$)".to_string()));
+                                }
+                            }
+                            '`' => {
+                                word.push('$');
+                                out.push(ShToken::Op("$(".to_string()));
+                                Self::lex(chars, Some('`'), out);
+                                out.push(ShToken::Op("$)".to_string()));
+                            }
+                            c => word.push(c),
+                        }
+                    }
+                }
+                '$' => {
+                    word.push('$');
+                    match chars.peek() {
+                        Some('{') => {
+                            chars.next();
+                            Self::read_until(chars, '}', &mut word);
+                        }
+                        Some('(') => {
+                            chars.next();
+                            if chars.peek() == Some(&'(') {
+                                chars.next();
+                                Self::skip_arithmetic(chars);
+                            } else {
+                                out.push(ShToken::Op("$(".to_string()));
+                                Self::lex(chars, Some(')'), out);
+                                out.push(ShToken::Op("$)".to_string()));
+                            }
+                        }
+                        _ => {}
+                    }
+                }
+                '`' => {
+                    word.push('$');
+                    out.push(ShToken::Op("$(".to_string()));
+                    Self::lex(chars, Some('`'), out);
+                    out.push(ShToken::Op("$)".to_string()));
+                }
+                '#' if word.is_empty() => {
+                    while matches!(chars.peek(), Some(c) if *c != '\n') {
+                        chars.next();
+                    }
+                }
+                ' ' | '\t' => Self::flush(&mut word, out),
+                '<' | '>' => {
+                    // File descriptor number, e.g. "2>&1"
+                    if word.chars().all(|c| c.is_ascii_digit()) {
+                        word.clear();
+                    }
+                    Self::flush(&mut word, out);
+
+                    let mut op = String::from(c);
+                    while let Some(n) = chars.peek().filter(|n| "<>&|-".contains(**n)) {
+                        op.push(*n);
+                        chars.next();
+                    }
+
+                    if op.starts_with("<<") && !op.starts_with("<<<") {
+                        while matches!(chars.peek(), Some(' ') | Some('\t')) {
+                            chars.next();
+                        }
+                        let mut delim = String::default();
+                        while let Some(n) = chars.peek().filter(|n| !n.is_whitespace() && !";|&)".contains(**n)) {
+                            if *n != '\'' && *n != '"' && *n != '\\' {
+                                delim.push(*n);
+                            }
+                            chars.next();
+                        }
+                        heredocs.push(delim);
+                        out.push(ShToken::Redir(false));
+                    } else if op.ends_with('&') && matches!(chars.peek(), Some(n) if n.is_ascii_digit() || *n == '-') {
+                        chars.next();
+                        out.push(ShToken::Redir(false));
+                    } else {
+                        // Closing descriptor "3>&-" has no target
+                        out.push(ShToken::Redir(!op.ends_with("&-")));
+                    }
+                }
+                '(' if word.is_empty() && chars.peek() == Some(&'(') => {
+                    chars.next();
+                    Self::skip_arithmetic(chars);
+                }
+                '\n' | ';' | '&' | '|' | '(' | ')' => {
+                    Self::flush(&mut word, out);
+
+                    let mut op = String::from(c);
+                    if c != '\n' && chars.peek() == Some(&c) {
+                        op.push(c);
+                        chars.next();
+                    }
+                    out.push(ShToken::Op(op));
+
+                    // Here-document bodies are not commands
+                    if c == '\n' {
+                        for delim in heredocs.drain(..) {
+                            let mut line = String::default();
+                            for n in chars.by_ref() {
+                                if n != '\n' {
+                                    line.push(n);
+                                    continue;
+                                }
+                                if line.trim() == delim {
+                                    break;
+                                }
+                                line.clear();
+                            }
+                        }
+                    }
+                }
+                c => word.push(c),
+            }
+        }
+
+        Self::flush(&mut word, out);
+    }
+
+    /// Returns true if the word is a variable assignment
+    fn is_assignment(w: &str) -> bool {
+        match w.split_once('=') {
+            Some((name, _)) => {
+                !name.is_empty()
+                    && !name.starts_with(|c: char| c.is_ascii_digit())
+                    && name.chars().all(|c| c.is_alphanumeric() || c == '_' || c == '+')
+            }
+            None => false,
+        }
+    }
+
+    /// Find command names in the command positions
+    fn parse(&mut self, tokens: &[ShToken]) -> (Vec<String>, Vec<String>) {
+        let mut commands: Vec<String> = Vec::default();
+        let mut sources: Vec<String> = Vec::default();
+
+        let mut cmd_pos = true;
+        let mut wrapper = false;
+        let mut skip_next = false;
+        let mut source_next = false;
+        let mut case_pending = false;
+        let mut case_pattern = false;
+        let mut case_depth = 0;
+        let mut subst: Vec<(bool, bool)> = Vec::default();
+
+        let mut i = 0;
+        while i < tokens.len() {
+            let tkn = &tokens[i];
+            i += 1;
+
+            let w = match tkn {
+                ShToken::Redir(target) => {
+                    skip_next = *target;
+                    continue;
+                }
+                ShToken::Op(op) => {
+                    skip_next = false;
+
+                    // Command substitution is a command on its own inside the current one
+                    if op == "$(" {
+                        subst.push((cmd_pos, wrapper));
+                    } else if op == "$)" {
+                        (cmd_pos, wrapper) = subst.pop().unwrap_or((true, false));
+                        continue;
+                    }
+
+                    if case_depth > 0 && (op == ";;" || op == ";&") {
+                        case_pattern = true;
+                    }
+                    if op != "(" || !case_pattern {
+                        cmd_pos = true;
+                        wrapper = false;
+                    }
+                    continue;
+                }
+                ShToken::Word(w) => w,
+            };
+
+            if skip_next {
+                skip_next = false;
+                continue;
+            }
+
+            if source_next {
+                source_next = false;
+                sources.push(w.to_owned());
+                continue;
+            }
+
+            if !cmd_pos {
+                if case_pending && w == "in" {
+                    case_pending = false;
+                    case_pattern = true;
+                    case_depth += 1;
+                    cmd_pos = true;
+                }
+                continue;
+            }
+
+            if w == "esac" {
+                case_depth -= 1;
+                case_pattern = false;
+                continue;
+            }
+
+            // Patterns of "case" are up to the closing parenthesis
+            if case_pattern {
+                while i < tokens.len() && tokens[i] != ShToken::Op(")".to_string()) {
+                    i += 1;
+                }
+                i += 1;
+                case_pattern = false;
+                continue;
+            }
+
+            // Function definition "name() {" or "function name {"
+            if tokens.get(i) == Some(&ShToken::Op("(".to_string())) && tokens.get(i + 1) == Some(&ShToken::Op(")".to_string())) {
+                self.functions.insert(w.to_owned());
+                i += 2;
+                continue;
+            }
+
+            if w == "function" {
+                if let Some(ShToken::Word(name)) = tokens.get(i) {
+                    self.functions.insert(name.to_owned());
+                }
+                cmd_pos = false;
+                continue;
+            }
+
+            if SH_RESERVED.contains(&w.as_str()) || Self::is_assignment(w) {
+                continue;
+            }
+
+            // Options and arguments of the wrappers, e.g. "timeout -s KILL 10 foo"
+            if wrapper && (w.starts_with('-') || w.starts_with(|c: char| c.is_ascii_digit())) {
+                continue;
+            }
+
+            match w.as_str() {
+                "case" => {
+                    case_pending = true;
+                    cmd_pos = false;
+                }
+                "for" | "select" => cmd_pos = false,
+                "." | "source" => {
+                    source_next = true;
+                    cmd_pos = false;
+                }
+                _ => {
+                    commands.push(w.to_owned());
+                    wrapper = SH_WRAPPERS.contains(&w.as_str());
+                    cmd_pos = wrapper;
+                }
+            }
+        }
+
+        (commands, sources)
+    }
+
+    /// Trace commands of the script and all the scripts it sources
+    fn trace(&mut self, script: &Path) {
+        let mut scripts: Vec<PathBuf> = vec![script.to_path_buf()];
+        let mut commands: Vec<(String, PathBuf)> = Vec::default();
+        let mut i = 0;
+
+        while i < scripts.len() {
+            let s = scripts[i].to_owned();
+            i += 1;
+
+            let mut tokens: Vec<ShToken> = Vec::default();
+            Self::lex(&mut fs::read_to_string(&s).unwrap_or_default().chars().peekable(), None, &mut tokens);
+
+            let (cmds, srcs) = self.parse(&tokens);
+            commands.extend(cmds.into_iter().map(|c| (c, s.to_owned())));
+
+            for src in srcs {
+                if src.contains('$') {
+                    log::warn!("Unable to resolve dynamically sourced file {} in {:?}", src, s);
+                    continue;
+                }
+
+                let p = if src.contains('/') { PathBuf::from(&src) } else { which(&src).unwrap_or_default() };
+                if p.is_file() && !scripts.contains(&p) {
+                    self.sources.push(p.to_owned());
+                    scripts.push(p);
+                } else if !p.is_file() {
+                    log::warn!("Sourced file {} of {:?} was not found", src, s);
+                }
+            }
+        }
+
+        // Functions are known only after all the scripts are parsed
+        let mut seen: HashSet<(String, PathBuf)> = HashSet::default();
+        for (cmd, s) in commands {
+            if SH_BUILTINS.contains(&cmd.as_str())
+                || self.functions.contains(&cmd)
+                || !seen.insert((cmd.to_owned(), s.to_owned()))
+            {
+                continue;
+            }
+
+            if cmd.contains('$') {
+                log::warn!("Unable to resolve dynamic command {} in {:?}", cmd, s);
+                continue;
+            }
+
+            let p = if cmd.starts_with('/') {
+                Some(PathBuf::from(&cmd)).filter(|p| p.exists())
+            } else if cmd.contains('/') {
+                log::debug!("Skipping relative command {} in {:?}", cmd, s);
+                continue;
+            } else {
+                which(&cmd)
+            };
+
+            match p {
+                Some(p) => {
+                    if !self.commands.contains(&p) {
+                        log::debug!("Script {:?} calls {:?}", s, p);
+                        self.commands.push(p);
+                    }
+                }
+                None => log::warn!("Command {} of {:?} was not found", cmd, s),
+            }
+        }
+    }
+}
+
+impl Scanner for ShellScanner {
+    /// Scan shell script for the external commands and the sourced files
+    fn scan(&mut self, pth: PathBuf) -> ScannerResult {
+        log::debug!("Scanning shell commands in {}", pth.to_str().unwrap());
+        self.trace(&pth);
+
+        let mut out = self.commands.to_owned();
+        out.extend(self.sources.to_owned());
+
+        ScannerResult::new(out)
+    }
+
+    /// Bogus trait implementation, does nothing in this case
+    fn exclude(&mut self, _: Vec<String>) -> &mut Self {
+        self
+    }
+
+    /// Dummy
+    fn contents(&mut self, _pkgname: String) -> Result<ScannerResult, std::io::Error> {
+        Err(std::io::Error::new(std::io::ErrorKind::Unsupported, ""))
+    }
+}
@@ -0,0 +1,95 @@
+/*
+Script targets. Finds interpreters from the "#!" line
+and resolves commands through $PATH inside the image.
+ */
+
+use std::{
+    fs::File,
+    io::{BufRead, BufReader},
+    os::unix::prelude::PermissionsExt,
+    path::{Path, PathBuf},
+};
+
+/// Default $PATH, if nothing else is known about the image
+pub const DEFAULT_PATH: &[&str] = &["/usr/local/sbin", "/usr/local/bin", "/usr/sbin", "/usr/bin", "/sbin", "/bin"];
+
+/// Linux allows only few levels of interpreter nesting
+const MAX_NESTING: usize = 4;
+
+/// Script interpreter, as defined in the shebang
+#[derive(Debug, Clone)]
+pub struct Shebang {
+    interp: PathBuf,
+    env: Option<PathBuf>,
+}
+
+impl Shebang {
+    /// Read shebang of a script. Returns None if the file is not a script.
+    pub fn read(script: &Path) -> Option<Self> {
+        let mut line = String::default();
+        BufReader::new(File::open(script).ok()?).read_line(&mut line).ok()?;
+
+        let line = line.strip_prefix("#!")?.trim();
+        let mut tokens = line.split_whitespace().map(|s| s.to_string());
+        let interp = PathBuf::from(tokens.next()?);
+
+        // "#!/usr/bin/env foo" needs foo from $PATH
+        if interp.file_name().unwrap_or_default() == "env" {
+            let cmd = tokens.find(|a| !a.starts_with('-') && !a.contains('='))?;
+            return match which(&cmd) {
+                Some(p) => Some(Shebang { interp: p, env: Some(interp) }),
+                None => {
+                    log::warn!("Unable to find \"{}\" in $PATH for {:?}", cmd, script);
+                    None
+                }
+            };
+        }
+
+        Some(Shebang { interp, env: None })
+    }
+
+    /// Get all the interpreters a script needs, including
+    /// "env" and interpreters of the interpreters.
+    pub fn chain(script: &Path) -> Vec<PathBuf> {
+        let mut out: Vec<PathBuf> = Vec::default();
+        let mut script = script.to_path_buf();
+
+        while let Some(sb) = Shebang::read(&script) {
+            if out.len() >= MAX_NESTING || out.contains(&sb.interp) {
+                break;
+            }
+
+            if let Some(env) = sb.env {
+                out.push(env);
+            }
+            out.push(sb.interp.to_owned());
+            script = sb.interp;
+        }
+
+        out
+    }
+
+    /// Find an interpreter of a script by its name prefix, e.g. "python" or "perl"
+    pub fn find_interp(script: &Path, name: &str) -> Option<PathBuf> {
+        Shebang::chain(script)
+            .into_iter()
+            .find(|i| i.file_name().unwrap_or_default().to_str().unwrap_or_default().starts_with(name))
+    }
+}
+
+/// Find a command in $PATH of the image
+pub fn which(cmd: &str) -> Option<PathBuf> {
+    if cmd.contains('/') {
+        let p = PathBuf::from(cmd);
+        return if p.is_file() { Some(p) } else { None };
+    }
+
+    for d in DEFAULT_PATH {
+        let p = Path::new(d).join(cmd);
+        if p.is_file() && p.metadata().map(|m| m.permissions().mode() & 0o111 != 0).unwrap_or(false) {
+            return Some(p);
+        }
+    }
+
+    None
+}
@@ -0,0 +1,81 @@
+/*
+Symbol usage analysis. Finds libraries, those are linked,
+but none of their symbols are actually used.
+ */
+
+use super::elfrd::ElfSymbols;
+use std::{
+    collections::{HashMap, HashSet},
+    path::PathBuf,
+};
+
+pub struct SymbolUsage {
+    objects: Vec<PathBuf>,
+    symbols: HashMap<PathBuf, ElfSymbols>,
+}
+
+impl SymbolUsage {
+    /// Objects of one process: main executable first, then its libraries
+    pub fn new(objects: &[PathBuf]) -> Self {
+        let mut su = SymbolUsage { objects: objects.to_vec(), symbols: HashMap::default() };
+        for o in objects {
+            match ElfSymbols::read(o) {
+                Ok(syms) => {
+                    su.symbols.insert(o.to_owned(), syms);
+                }
+                Err(err) => log::debug!("Unable to read symbols of {:?}: {}", o, err),
+            }
+        }
+
+        su
+    }
+
+    /// Get libraries, those are not supplying any symbols to the process.
+    ///
+    /// Starting from the main executable, a library becomes used as soon as it exports
+    /// any symbol, imported by an already used object. Everything what is left is linked,
+    /// but is not used, including libraries those are only needed by unused ones.
+    pub fn get_unused(&self) -> Vec<PathBuf> {
+        let main = match self.objects.first() {
+            Some(main) => main,
+            None => return vec![],
+        };
+
+        let mut used: HashSet<&PathBuf> = HashSet::from([main]);
+        let mut imported: HashSet<&String> = HashSet::default();
+        if let Some(syms) = self.symbols.get(main) {
+            imported.extend(syms.get_imported());
+        }
+
+        loop {
+            let mut changed = false;
+            for lib in &self.objects[1..] {
+                if used.contains(lib) {
+                    continue;
+                }
+
+                // Library without symbol table cannot be judged
+                let syms = match self.symbols.get(lib) {
+                    Some(syms) => syms,
+                    None => {
+                        used.insert(lib);
+                        changed = true;
+                        continue;
+                    }
+                };
+
+                if syms.get_exported().iter().any(|s| imported.contains(s)) {
+                    used.insert(lib);
+                    imported.extend(syms.get_imported());
+                    changed = true;
+                }
+            }
+
+            if !changed {
+                break;
+            }
+        }
+
+        self.objects.iter().filter(|o| !used.contains(o)).cloned().collect()
+    }
+}
@@ -0,0 +1,88 @@
+use super::{
+    pkgdb::{DbPackage, PackageDb},
+    traceitf::{self, PkgDepTrace},
+};
+use crate::{profile::DepsPolicy, rootfs::RootFS};
+use std::{
+    collections::{HashMap, HashSet},
+    path::PathBuf,
+};
+
+pub struct DbPackageTrace<'a, D: PackageDb> {
+    db: &'a D,
+    exclude: HashSet<String>,
+    policy: DepsPolicy,
+    reasons: HashMap<String, String>,
+}
+
+impl<'a, D: PackageDb> DbPackageTrace<'a, D> {
+    pub fn new(db: &'a D) -> Self {
+        DbPackageTrace { db, exclude: HashSet::default(), policy: DepsPolicy::default(), reasons: HashMap::default() }
+    }
+
+    /// Find a package, which satisfies the requirement: a file path or a provided name.
+    /// Requirements are already stripped of the version constraints by the database.
+    fn resolve(req: &str, owners: &HashMap<PathBuf, String>, providers: &HashMap<String, Vec<String>>) -> Option<String> {
+        if req.starts_with('/') {
+            let mut paths = vec![PathBuf::from(req)];
+            paths.extend(RootFS::expand_target(PathBuf::from(req), false));
+            return paths.iter().find_map(|p| owners.get(p).cloned());
+        }
+
+        providers.get(req).and_then(|p| p.first().cloned())
+    }
+
+    /// Get resolved dependencies of a package, for the relation types of the policy
+    fn get_package_deps(
+        &self, pkg: &str, owners: &HashMap<PathBuf, String>, providers: &HashMap<String, Vec<String>>,
+    ) -> Vec<(&'static str, String)> {
+        let mut out: Vec<(&'static str, String)> = Vec::default();
+        let info = match self.db.get_package(pkg) {
+            Some(info) => info,
+            None => return out,
+        };
+
+        for (rel, policy_rel, reqs) in info.get_deps() {
+            if !self.policy.has_relation(policy_rel) {
+                continue;
+            }
+
+            for req in reqs {
+                match Self::resolve(req, owners, providers) {
+                    Some(dep) => out.push((rel, dep)),
+                    None => log::debug!("{} \"{}\" of {} is not satisfied by any package", rel, req, pkg),
+                }
+            }
+        }
+
+        out
+    }
+}
+
+impl<D: PackageDb> PkgDepTrace for DbPackageTrace<'_, D> {
+    fn trace(&mut self, pkgname: String) -> Vec<String> {
+        log::info!("Getting dependencies for a package {}", pkgname);
+        let owners = self.db.get_file_owners();
+        let providers = self.db.get_providers();
+
+        let (data, reasons) = traceitf::walk_dependencies(&pkgname, self.policy.get_depth(), &self.exclude, |p| {
+            self.get_package_deps(p, &owners, &providers)
+        });
+        self.reasons = reasons;
+        data
+    }
+
+    fn exclude(&mut self, pkgs: Vec<String>) -> &mut dyn PkgDepTrace {
+        self.exclude.extend(pkgs);
+        self
+    }
+
+    fn set_policy(&mut self, policy: DepsPolicy) -> &mut dyn PkgDepTrace {
+        self.policy = policy;
+        self
+    }
+
+    fn get_reasons(&self) -> &HashMap<String, String> {
+        &self.reasons
+    }
+}
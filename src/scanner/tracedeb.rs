@@ -1,71 +1,72 @@
-use super::traceitf::PkgDepTrace;
-use std::{collections::HashSet, process::Command};
+use super::{
+    dpkgstatus::DpkgStatus,
+    traceitf::{self, PkgDepTrace},
+};
+use crate::profile::DepsPolicy;
+use std::collections::{HashMap, HashSet};
 
-pub struct DebPackageTrace {
-    data: HashSet<String>,
+/// Relations of a package, in the order they are followed
+const DEB_RELATIONS: &[&str] = &["Pre-Depends", "Depends", "Recommends", "Suggests"];
+
+pub struct DebPackageTrace<'a> {
+    status: &'a DpkgStatus,
     exclude: HashSet<String>,
+    policy: DepsPolicy,
+    reasons: HashMap<String, String>,
 }
 
-impl DebPackageTrace {
-    pub fn new() -> Self {
-        DebPackageTrace { data: HashSet::default(), exclude: HashSet::default() }
+impl<'a> DebPackageTrace<'a> {
+    pub fn new(status: &'a DpkgStatus) -> Self {
+        DebPackageTrace { status, exclude: HashSet::default(), policy: DepsPolicy::default(), reasons: HashMap::default() }
     }
 
-    /// Get list of package dependencies for the first nearby level
-    ///
-    /// NOTE: currently it is quite bad way by hammering with apt,
-    ///       but it works and is okey-ish for the time being.
-    ///       This needs to be rewritten by slurping the entire /var/lib/dpkg/status
-    ///       and the processing it at once.
-    fn get_dependencies(&mut self, pkg: String, start: bool) -> Vec<String> {
-        if start {
-            self.data.clear();
-        }
-
-        let mut c = Command::new("apt");
-        c.args(["depends", pkg.as_str()]);
+    /// Get resolved dependencies of a package, for the relation types of the policy
+    fn get_package_deps(&self, pkg: &str) -> Vec<(&'static str, String)> {
+        let mut out: Vec<(&'static str, String)> = Vec::default();
+        let info = match self.status.get_package(pkg) {
+            Some(info) => info,
+            None => return out,
+        };
+        let arch = info.get_field("Architecture").unwrap_or_default();
 
-        match c.output() {
-            Ok(out) => {
-                if let Ok(out) = String::from_utf8(out.stdout) {
-                    for l in out.lines().map(|s| s.trim().to_string()).collect::<Vec<String>>() {
-                        if l.to_lowercase().starts_with("depends:") {
-                            let l = l.split(' ').collect::<Vec<&str>>();
-                            if l.len() > 2 {
-                                let pkgname = l[1].to_string();
-                                if !self.data.contains(&pkgname) {
-                                    self.data.insert(pkgname.to_owned());
-                                    self.get_dependencies(pkgname, false);
-                                }
-                            }
-                        }
-                    }
+        for rel in DEB_RELATIONS.iter().filter(|r| self.policy.has_relation(r)) {
+            for group in info.get_relations(rel) {
+                match self.status.resolve(&group, arch) {
+                    Some(dep) => out.push((rel, dep.get_name().to_string())),
+                    None => log::debug!(
+                        "{} \"{}\" of {} is not satisfied by any installed package",
+                        rel,
+                        group.iter().map(|d| d.get_name()).collect::<Vec<&str>>().join(" | "),
+                        pkg
+                    ),
                 }
             }
-            Err(err) => {
-                log::error!("Cannot get package dependencies: {}", err);
-                return vec![];
-            }
         }
 
-        let mut data = self.data.clone().into_iter().collect::<Vec<String>>();
-        data.sort();
-        data
+        out
     }
 }
 
-impl PkgDepTrace for DebPackageTrace {
+impl PkgDepTrace for DebPackageTrace<'_> {
     fn trace(&mut self, pkgname: String) -> Vec<String> {
         log::info!("Getting dependencies for a package {}", pkgname);
-
-        let mut d = self.get_dependencies(pkgname, true);
-        d.retain(|p| !self.exclude.contains(p));
-
-        d
+        let (data, reasons) =
+            traceitf::walk_dependencies(&pkgname, self.policy.get_depth(), &self.exclude, |p| self.get_package_deps(p));
+        self.reasons = reasons;
+        data
     }
 
     fn exclude(&mut self, pkgs: Vec<String>) -> &mut dyn PkgDepTrace {
         self.exclude.extend(pkgs);
         self
     }
+
+    fn set_policy(&mut self, policy: DepsPolicy) -> &mut dyn PkgDepTrace {
+        self.policy = policy;
+        self
+    }
+
+    fn get_reasons(&self) -> &HashMap<String, String> {
+        &self.reasons
+    }
 }
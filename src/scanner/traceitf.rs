@@ -1,12 +1,58 @@
-use std::path::PathBuf;
+use crate::{profile::DepsPolicy, rootfs::RootFS};
+use std::{
+    collections::{HashMap, HashSet, VecDeque},
+    path::PathBuf,
+};
 
 /// Package dependency trace
 pub trait PkgDepTrace {
     fn trace(&mut self, pkgname: String) -> Vec<String>;
     fn exclude(&mut self, pkgs: Vec<String>) -> &mut dyn PkgDepTrace;
+
+    /// Set relation types to follow and the maximum depth
+    fn set_policy(&mut self, policy: DepsPolicy) -> &mut dyn PkgDepTrace;
+
+    /// Get relation of each traced package, which pulled it in, e.g. "Depends of bash"
+    fn get_reasons(&self) -> &HashMap<String, String>;
 }
 
 pub trait PkgFileTrace {
     /// Return a package name, to which this file belongs to
     fn trace(&mut self, filename: PathBuf) -> Option<String>;
+
+    /// Return a package name of the file. Symlinks, those are not owned by any package
+    /// (e.g. made by update-alternatives), are followed to the owned target.
+    fn trace_links(&mut self, filename: PathBuf) -> Option<String> {
+        RootFS::get_link_chain(&filename).into_iter().find_map(|p| self.trace(p))
+    }
+}
+
+/// Walk the dependency graph of the package breadth-first, down to the maximum depth.
+/// `get_deps` returns resolved dependencies of a package with their relation types.
+///
+/// Returns sorted dependency packages and the relation, which pulled in each of them.
+pub fn walk_dependencies<F>(
+    pkg: &str, depth: Option<usize>, exclude: &HashSet<String>, mut get_deps: F,
+) -> (Vec<String>, HashMap<String, String>)
+where
+    F: FnMut(&str) -> Vec<(&'static str, String)>,
+{
+    let mut reasons: HashMap<String, String> = HashMap::default();
+    let mut queue: VecDeque<(String, usize)> = VecDeque::from([(pkg.to_string(), 0)]);
+    while let Some((p, level)) = queue.pop_front() {
+        if matches!(depth, Some(depth) if level >= depth) {
+            continue;
+        }
+
+        for (rel, dep) in get_deps(&p) {
+            if dep != pkg && !exclude.contains(&dep) && !reasons.contains_key(&dep) {
+                reasons.insert(dep.to_owned(), format!("{} of {}", rel, p));
+                queue.push_back((dep, level + 1));
+            }
+        }
+    }
+
+    let mut data = reasons.keys().cloned().collect::<Vec<String>>();
+    data.sort();
+    (data, reasons)
 }
@@ -0,0 +1,91 @@
+/*
+Symbol version verification. Checks if the kept libraries
+are providing all the version nodes (GLIBC_2.34, GLIBCXX_3.4.30 etc)
+which are required by the kept ELF objects.
+ */
+
+use super::elfrd::ElfInfo;
+use std::{
+    collections::{HashMap, HashSet},
+    fs,
+    path::PathBuf,
+};
+
+/// Version requirement which cannot be satisfied
+pub struct VersionProblem {
+    pub object: PathBuf,
+    pub soname: String,
+    pub version: Option<String>, // None if the whole library is not kept
+}
+
+pub struct VersionCheck {
+    objects: Vec<ElfInfo>,
+    provided: HashMap<String, HashSet<String>>,
+}
+
+impl VersionCheck {
+    /// Read all ELF objects among the given paths
+    pub fn new(paths: &[PathBuf]) -> Self {
+        let mut vc = VersionCheck { objects: Vec::default(), provided: HashMap::default() };
+        let mut visited: HashSet<PathBuf> = HashSet::default();
+
+        for p in paths {
+            if ElfInfo::read_header(p).is_none() {
+                continue;
+            }
+
+            // Library is known by its soname and by all the names it is linked as
+            let obj = match ElfInfo::read(p) {
+                Ok(obj) => obj,
+                Err(err) => {
+                    log::debug!("Skipping {:?}: {}", p, err);
+                    continue;
+                }
+            };
+
+            let mut names: Vec<String> = Vec::default();
+            if let Some(soname) = obj.get_soname() {
+                names.push(soname.to_owned());
+            }
+            if let Some(fname) = p.file_name() {
+                names.push(fname.to_str().unwrap_or_default().to_string());
+            }
+
+            for n in names {
+                vc.provided.entry(n).or_default().extend(obj.get_ver_defined().iter().cloned());
+            }
+
+            if visited.insert(fs::canonicalize(p).unwrap_or(p.to_owned())) {
+                vc.objects.push(obj);
+            }
+        }
+
+        vc
+    }
+
+    /// Get all version requirements, which are not satisfied by the kept libraries.
+    /// Libraries, those are needed but not kept at all, are also reported.
+    pub fn check(&self) -> Vec<VersionProblem> {
+        let mut problems: Vec<VersionProblem> = Vec::default();
+
+        for obj in &self.objects {
+            for soname in obj.get_needed().iter().filter(|n| !n.contains('/') && !self.provided.contains_key(*n)) {
+                problems.push(VersionProblem { object: obj.get_path().to_path_buf(), soname: soname.to_owned(), version: None });
+            }
+
+            for (soname, versions) in obj.get_ver_needed() {
+                if let Some(provided) = self.provided.get(soname) {
+                    for v in versions.iter().filter(|v| !provided.contains(*v)) {
+                        problems.push(VersionProblem {
+                            object: obj.get_path().to_path_buf(),
+                            soname: soname.to_owned(),
+                            version: Some(v.to_owned()),
+                        });
+                    }
+                }
+            }
+        }
+
+        problems
+    }
+}
@@ -0,0 +1,143 @@
+/*
+Variables of the profiles.
+
+Strings of a profile may refer to "${VAR}", which is taken from the
+environment, from the built-ins, detected in the root filesystem, or
+from the command line, each of them overriding the previous one.
+Undefined variables are errors. "$${VAR}" is a literal "${VAR}".
+ */
+
+use crate::scanner::{backend, elfarch::ElfArch};
+use std::{
+    collections::HashMap,
+    env,
+    io::{Error, ErrorKind},
+    path::Path,
+};
+
+#[derive(Clone, Debug)]
+pub struct Variables {
+    vars: HashMap<String, String>,
+}
+
+impl Variables {
+    /// Variables from the environment
+    pub fn new() -> Self {
+        Variables { vars: env::vars().collect() }
+    }
+
+    /// Detect built-ins from the image under the root: ARCH, MULTIARCH and OS_ID
+    pub fn detect(&mut self, root: &Path) -> &mut Self {
+        match ElfArch::detect(root) {
+            Some(arch) => {
+                self.set("ARCH", arch.get_uname());
+                self.set("MULTIARCH", arch.get_multiarch(root));
+            }
+            None => log::warn!("Unable to detect architecture of the image"),
+        }
+
+        match backend::get_os_ids(root).first() {
+            Some(id) => {
+                self.set("OS_ID", id);
+            }
+            None => log::warn!("Unable to detect distribution of the image"),
+        }
+
+        self
+    }
+
+    /// Set a variable
+    pub fn set(&mut self, name: &str, value: &str) -> &mut Self {
+        self.vars.insert(name.to_string(), value.to_string());
+        self
+    }
+
+    /// Set a variable from "KEY=VALUE" definition
+    pub fn define(&mut self, def: &str) -> Result<&mut Self, Error> {
+        match def.split_once('=') {
+            Some((name, value)) if Self::is_name(name) => Ok(self.set(name, value)),
+            _ => Err(Error::new(ErrorKind::InvalidInput, format!("Invalid variable definition \"{}\", expected KEY=VALUE", def))),
+        }
+    }
+
+    /// Variable names are letters, digits and underscores
+    fn is_name(name: &str) -> bool {
+        !name.is_empty() && name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_')
+    }
+
+    /// Substitute all variables in the string
+    pub fn expand(&self, s: &str) -> Result<String, Error> {
+        let mut out = String::default();
+        let mut rest = s;
+
+        while let Some(i) = rest.find('$') {
+            out.push_str(&rest[..i]);
+            rest = &rest[i..];
+
+            if let Some(r) = rest.strip_prefix("$${") {
+                out.push_str("${");
+                rest = r;
+            } else if let Some(r) = rest.strip_prefix("${") {
+                let (name, r) = r
+                    .split_once('}')
+                    .ok_or_else(|| Error::new(ErrorKind::InvalidData, format!("Unterminated variable in \"{}\"", s)))?;
+                if !Self::is_name(name) {
+                    return Err(Error::new(ErrorKind::InvalidData, format!("Invalid variable name \"{}\" in \"{}\"", name, s)));
+                }
+
+                match self.vars.get(name) {
+                    Some(v) => out.push_str(v),
+                    None => {
+                        return Err(Error::new(ErrorKind::InvalidData, format!("Undefined variable ${{{}}} in \"{}\"", name, s)))
+                    }
+                }
+                rest = r;
+            } else {
+                out.push('$');
+                rest = &rest[1..];
+            }
+        }
+        out.push_str(rest);
+
+        Ok(out)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Variables;
+
+    fn vars() -> Variables {
+        let mut v = Variables { vars: Default::default() };
+        v.set("ARCH", "x86_64").set("OS_ID", "debian");
+        v
+    }
+
+    #[test]
+    fn test_expand() {
+        assert_eq!(vars().expand("/usr/lib/${ARCH}-linux-gnu").unwrap(), "/usr/lib/x86_64-linux-gnu");
+        assert_eq!(vars().expand("${OS_ID}/${ARCH}").unwrap(), "debian/x86_64");
+        assert_eq!(vars().expand("$HOME and $").unwrap(), "$HOME and $");
+    }
+
+    #[test]
+    fn test_expand_escaped() {
+        assert_eq!(vars().expand("$${ARCH}").unwrap(), "${ARCH}");
+        assert_eq!(vars().expand("$${UNDEFINED} ${ARCH}").unwrap(), "${UNDEFINED} x86_64");
+    }
+
+    #[test]
+    fn test_expand_errors() {
+        assert!(vars().expand("${UNDEFINED}").is_err());
+        assert!(vars().expand("${ARCH").is_err());
+        assert!(vars().expand("${A-B}").is_err());
+    }
+
+    #[test]
+    fn test_define() {
+        let mut v = vars();
+        assert_eq!(v.define("ARCH=aarch64").unwrap().expand("${ARCH}").unwrap(), "aarch64");
+        assert!(v.define("ARCH").is_err());
+        assert!(v.define("=x").is_err());
+    }
+}